@@ -9,22 +9,32 @@
     rust_2018_idioms
 )]
 
+use arrayvec::{ArrayString, ArrayVec};
 use asr::{
     file_format::pe,
     future::{next_tick, retry},
-    settings::Gui,
+    settings::{self, Gui},
     signature::Signature,
     time::Duration,
     timer::{self, TimerState},
-    watcher::Watcher,
+    watcher::{Pair, Watcher},
     Address, Address64, Process,
 };
 
+use core::fmt::{self, Write};
+
 asr::panic_handler!();
 asr::async_main!(nightly);
 
 const PROCESS_NAMES: &[&str] = &["SonicColorsUltimate.exe", "Sonic Colors - Ultimate.exe"];
 
+/// Consecutive good reads needed after attaching before start, split and reset are evaluated
+const SETTLE_TICKS: u32 = 10;
+/// Consecutive updates the pointer chain can fail to resolve before the hook is reported as degraded
+const HOOK_DEGRADED_TICKS: u32 = 120;
+/// Consecutive updates the pointer chain can fail to resolve before the signature scan is run again
+const HOOK_RESCAN_TICKS: u32 = 600;
+
 async fn main() {
     let mut settings = Settings::register();
 
@@ -38,7 +48,11 @@ async fn main() {
                 let mut watchers = Watchers::default();
 
                 // Perform memory scanning to look for the addresses we need
-                let addresses = Addresses::init(&process).await;
+                HookState::Scanning.publish();
+                let mut addresses = Addresses::init(&process).await;
+                let mut hook_state = HookState::Hooked;
+                let mut failed_resolutions: u32 = 0;
+                hook_state.publish();
 
                 loop {
                     // Splitting logic. Adapted from OG LiveSplit:
@@ -48,10 +62,70 @@ async fn main() {
                     // 3. If reset does not return true, then the split action will be run.
                     // 4. If the timer is currently not running (and not paused), then the start action will be run.
                     settings.update();
-                    update_loop(&process, &addresses, &mut watchers);
+                    let resolved = update_loop(&process, &addresses, &mut watchers);
+                    if watchers.settleticks >= SETTLE_TICKS {
+                        log_edges(&mut watchers, &settings);
+                    }
+                    if resolved {
+                        update_telemetry(&process, &addresses, &mut watchers, &settings);
+                    }
+
+                    // Keep an eye on the pointer chain: if it stops resolving for too long,
+                    // the singleton has most likely moved and the signature scan is run again
+                    failed_resolutions = match resolved {
+                        true => 0,
+                        _ => failed_resolutions.saturating_add(1),
+                    };
+
+                    let new_hook_state = match failed_resolutions >= HOOK_DEGRADED_TICKS {
+                        true => HookState::Degraded,
+                        _ => HookState::Hooked,
+                    };
+                    if new_hook_state != hook_state {
+                        hook_state = new_hook_state;
+                        hook_state.publish();
+                    }
+
+                    if failed_resolutions >= HOOK_RESCAN_TICKS {
+                        HookState::Scanning.publish();
+                        addresses = Addresses::init(&process).await;
+                        failed_resolutions = 0;
+                        watchers.settleticks = 0;
+                        hook_state = HookState::Hooked;
+                        hook_state.publish();
+                    }
+
+                    // Right after attaching, the watchers hold defaults, and the first good reads would
+                    // look like transitions from them (e.g. from `Levels::None` into a stage).
+                    // Wait for the memory to read consistently before acting on anything.
+                    if watchers.settleticks < SETTLE_TICKS {
+                        next_tick().await;
+                        continue;
+                    }
+
+                    // If the timer was already going when we attached, pick the run back up
+                    if !watchers.resumed {
+                        watchers.resumed = true;
+                        match timer::state() {
+                            TimerState::NotRunning => SavedRun::clear(&mut watchers),
+                            _ => resume_run(&mut watchers, &settings),
+                        }
+                    }
+
+                    // Keep a copy of the run state that survives a reload of the auto splitter,
+                    // whether the run was started by us or by hand
+                    let running = timer::state() != TimerState::NotRunning;
+                    match running {
+                        true => SavedRun::store(&mut watchers),
+                        _ if watchers.timerrunning => SavedRun::clear(&mut watchers),
+                        _ => (),
+                    }
+                    watchers.timerrunning = running;
 
                     let timer_state = timer::state();
                     if timer_state == TimerState::Running || timer_state == TimerState::Paused {
+                        latch_final_hit(&mut watchers, &settings);
+
                         if let Some(is_loading) = is_loading(&watchers, &settings) {
                             if is_loading {
                                 timer::pause_game_time()
@@ -61,20 +135,49 @@ async fn main() {
                         }
 
                         if let Some(game_time) = game_time(&watchers, &settings) {
+                            watchers.lastgametime = game_time;
                             timer::set_game_time(game_time)
                         }
 
-                        if reset(&watchers, &settings) {
+                        // Splits that don't depend on the level are queued, so that they never
+                        // take the place of a level split happening on the same update
+                        if park_tokens_reached(&watchers, &settings) {
+                            watchers.parktokensreached = true;
+                            watchers.queuedsplits += 1;
+                        }
+                        if emerald_collected(&watchers, &settings) {
+                            watchers.queuedsplits += 1;
+                        }
+
+                        if reset_guarded(&mut watchers, &settings) {
+                            debug_print(&settings, format_args!("reset: fired"));
                             timer::reset()
                         } else if split(&watchers, &settings) {
-                            timer::split()
+                            debug_print(&settings, format_args!("split: fired"));
+                            timer::split();
+
+                            if !watchers.runended && final_stage(&watchers, &settings) {
+                                watchers.runended = true;
+                                watchers.runlog.print();
+                            }
+                        } else if watchers.queuedsplits > 0 {
+                            watchers.queuedsplits -= 1;
+                            debug_print(&settings, format_args!("split: fired (queued)"));
+                            timer::split();
+                        } else if custom_split(&process, &addresses, &mut watchers, &settings) {
+                            timer::split();
                         }
                     }
 
                     if timer::state() == TimerState::NotRunning && start(&watchers, &settings) {
+                        debug_print(&settings, format_args!("start: fired"));
                         timer::start();
                         timer::pause_game_time();
 
+                        watchers.startoffset = start_offset(&watchers, &settings);
+                        watchers.lastgametime = watchers.startoffset;
+                        timer::set_game_time(watchers.startoffset);
+
                         if let Some(is_loading) = is_loading(&watchers, &settings) {
                             if is_loading {
                                 timer::pause_game_time()
@@ -95,13 +198,74 @@ async fn main() {
 struct Watchers {
     levelid: Watcher<Levels>,
     igt: Watcher<Duration>,
-    goalringreached: Watcher<bool>,
+    stageflags: Watcher<StageFlags>,
+    paused: Watcher<bool>,
     eggshuttle_totalstages: Watcher<u8>,
     eggshuttle_progressiveid: Watcher<u8>,
+    eggshuttle_state: Watcher<EggShuttleState>,
     runstart: Watcher<u8>,
     tr1rank: Watcher<i8>,
+    tr1rank_read: Watcher<bool>,
+    chaosemeralds: Watcher<u8>,
+    rivalrush: Watcher<RivalRushResult>,
+    wisp: Watcher<Wisp>,
+    wispcapsules: Watcher<[u8; Wisp::COUNT]>,
+    bosshealth: Watcher<u32>,
+    bossphase: Watcher<u8>,
+    /// Act IGT at the final hit on a boss that splits on it, which ends the boss's time
+    bosslatch: Option<Duration>,
+    /// The values the watchers were last updated with
+    snapshot: Option<Snapshot>,
+    /// Color Power activations in the current run, indexed by `Wisp as usize - 1`
+    wispuses: [u16; Wisp::COUNT],
+    parktokens: Watcher<u32>,
+    unlocks: Watcher<u32>,
+    parktokens_runstart: u32,
+    /// Whether the Park Token threshold was already reached in this run
+    parktokensreached: bool,
+    /// Splits waiting for an update without a level split
+    queuedsplits: u32,
+    unlocks_runstart: u32,
+    levelcleared: Watcher<bool>,
+    /// The level `levelcleared` refers to
+    clearedlevel: Levels,
+    /// The Egg Shuttle position of `clearedlevel`
+    clearedstage: u8,
     accumulatedigt: Duration,
+    startoffset: Duration,
+    lastgametime: Duration,
+    runended: bool,
+    edges: bool,
+    settleticks: u32,
+    telemetryticks: u32,
+    customsplits: CustomSplits,
+    resumed: bool,
+    /// Whether the timer was running on the previous update
+    timerrunning: bool,
+    /// The run state last copied to the settings map
+    savedrun: Option<SavedRun>,
+    /// Set when a run was resumed without a saved state: LiveSplit's own game time is kept
+    /// instead of being overwritten with a total that lacks the previous acts
+    gametimeunknown: bool,
+    /// Number of updates since the IGT last went up
+    igtstillticks: u32,
+    runlog: RunLog,
+    resetpending: bool,
     currentgamemode: GameMode,
+    /// Stages selected for the custom Egg Shuttle split filter, one bit each
+    eggshuttlestages: u64,
+    definitionticks: u32,
+}
+
+impl Watchers {
+    /// Old and current state of a single stage status flag
+    fn stage_flag(&self, flag: StageFlags) -> Option<Pair<bool>> {
+        let stageflags = self.stageflags.pair.as_ref()?;
+        Some(Pair {
+            old: stageflags.old.contains(flag),
+            current: stageflags.current.contains(flag),
+        })
+    }
 }
 
 struct Addresses {
@@ -122,32 +286,188 @@ impl Addresses {
             (main_module_base, main_module_size as u64)
         };
 
-        const SIG: Signature<5> = Signature::new("76 0C 48 8B 0D");
-        let mut ptr = retry(|| SIG.scan_process_range(process, main_module)).await + 5;
-        ptr = ptr + 0x4 + retry(|| process.read::<i32>(ptr)).await;
+        // Signatures are tried in order of priority, each on every one of its matches, and a
+        // match is only accepted once the pointer chain behind it can be read. If nothing
+        // matches in time, the runner is told so instead of being left with a silently broken
+        // autosplitter. A match whose chain isn't populated yet is simply retried.
+        let mut rounds: u32 = 0;
+        loop {
+            let mut pending = false;
+            for scan in SCANNERS {
+                let mut range = main_module;
+                while let Some((hit, base_address)) = scan(process, range) {
+                    let skipped = hit.value() + 1 - range.0.value();
+                    range = (hit + 1, range.1.saturating_sub(skipped));
+
+                    let Some(base_address) = base_address else {
+                        continue;
+                    };
+                    match Self::validate(process, base_address) {
+                        Validation::Valid => return Self { base_address },
+                        Validation::NotReady => pending = true,
+                        Validation::Invalid => (),
+                    }
+                }
+            }
+
+            if !pending {
+                rounds += 1;
+            }
+            if rounds == SCAN_TIMEOUT_ROUNDS {
+                HookState::Failed.publish();
+                asr::print_message(
+                    "Could not find the game's memory: this version of the game is not supported yet",
+                );
+            }
+
+            for _ in 0..SCAN_RETRY_TICKS {
+                next_tick().await;
+            }
+        }
+    }
+
+    /// Checks that the pointer chain behind a candidate address can be read. Either the
+    /// object holding the current level or the save data is enough, as both are already
+    /// there in menus, before the first stage is loaded.
+    fn validate(process: &Process, base_address: Address) -> Validation {
+        // Null pointers are expected while the game is starting up, failed reads are not
+        let follow = |address: Address| match process.read::<Address64>(address) {
+            Ok(next) if next.is_null() => Err(Validation::NotReady),
+            Ok(next) => Ok(next),
+            _ => Err(Validation::Invalid),
+        };
+
+        let check = || {
+            let addr_base = follow(base_address)?;
+            let addr_1 = follow(addr_base + 0x8)?;
+            match follow(addr_1 + 0x38) {
+                Err(Validation::NotReady) => (),
+                x => return x,
+            }
+
+            let addr_2 = follow(addr_1 + 0x8)?;
+            let addr_3 = follow(addr_2 + 0x10)?;
+            follow(addr_3 + 0x60)
+        };
 
-        Self { base_address: ptr }
+        match check() {
+            Ok(_) => Validation::Valid,
+            Err(x) => x,
+        }
     }
 }
 
+/// Number of scanning rounds after which the scan is reported as failed (about 30 seconds)
+const SCAN_TIMEOUT_ROUNDS: u32 = 60;
+/// Number of updates to wait between scanning rounds
+const SCAN_RETRY_TICKS: u32 = 60;
+
+/// Finds the first signature match in the given range, along with the base address
+/// resolved from it (if it could be)
+type Scanner = fn(&Process, (Address, u64)) -> Option<(Address, Option<Address>)>;
+
+/// Outcome of checking a signature match
+enum Validation {
+    Valid,
+    /// The pointer chain isn't populated yet, so the match can't be judged
+    NotReady,
+    Invalid,
+}
+
+/// Signature scans for the base address, in order of priority. Each one knows how to
+/// resolve the RIP-relative address of the singleton from its own match. Fallbacks belong
+/// here once they have been taken from another build of the game.
+const SCANNERS: &[Scanner] = &[|process, range| {
+    const SIG: Signature<5> = Signature::new("76 0C 48 8B 0D");
+    let hit = SIG.scan_process_range(process, range)?;
+    let ptr = hit + 5;
+    Some((hit, process.read::<i32>(ptr).ok().map(|x| ptr + 0x4 + x)))
+}];
+
 #[derive(Gui)]
 struct Settings {
     #[default = true]
     /// START: Auto start (Any%)
     start_anypercent: bool,
+    /// START: Any% - Start event
+    start_anypercent_event: AnyPercentStart,
+    #[default = false]
+    /// START: Any% - Allow starting from an existing save file
+    start_anypercent_existing_save: bool,
     #[default = true]
     /// START: Auto start (Sonic Simulator)
     start_sonic_simulator: bool,
+    #[default = false]
+    /// START: Sonic Simulator - Start on any act (per-world practice)
+    start_sonic_simulator_any_act: bool,
     #[default = true]
     /// START: Auto start (Egg Shuttle)
     start_egg_shuttle: bool,
     #[default = true]
+    /// START: Auto start (All Bosses / single zone / Rival Rush)
+    start_category: bool,
+    /// START: Start offset (Any%)
+    start_offset_anypercent: StartOffset,
+    /// START: Start offset (Sonic Simulator)
+    start_offset_sonic_simulator: StartOffset,
+    /// START: Start offset (Egg Shuttle)
+    start_offset_egg_shuttle: StartOffset,
+    /// START: Start offset (All Bosses / single zone / Rival Rush)
+    start_offset_category: StartOffset,
+    #[default = true]
     /// RESET: Auto reset (Any%)
     reset_anypercent: bool,
     #[default = true]
     /// RESET: Auto reset (Egg Shuttle)
     reset_egg_shuttle: bool,
     #[default = true]
+    /// RESET: Egg Shuttle - Reset on game over
+    reset_egg_shuttle_game_over: bool,
+    #[default = true]
+    /// RESET: Egg Shuttle - Reset on quit to menu
+    reset_egg_shuttle_quit: bool,
+    #[default = true]
+    /// RESET: Auto reset on a lost race (Rival Rush)
+    reset_rival_rush: bool,
+    #[default = true]
+    /// RESET: Never auto reset once the run has ended
+    reset_never_after_end: bool,
+    /// RESET: Never auto reset after split
+    reset_split_limit: ResetSplitLimit,
+    #[default = false]
+    /// RESET: Require the reset condition to hold for two consecutive updates
+    reset_confirm: bool,
+    /// CATEGORY: Run category (ignored in Egg Shuttle)
+    category: Category,
+    /// TIMING: Timing method
+    timing_method: TimingMethod,
+    /// TELEMETRY: Speed, position and boost gauge variables
+    telemetry_rate: TelemetryRate,
+    /// SPLIT: Split timing
+    split_timing: SplitTiming,
+    /// SPLIT: Split timing override (Any%)
+    split_timing_anypercent: SplitTimingOverride,
+    /// SPLIT: Split timing override (Sonic Simulator)
+    split_timing_sonic_simulator: SplitTimingOverride,
+    /// SPLIT: Split timing override (Egg Shuttle)
+    split_timing_egg_shuttle: SplitTimingOverride,
+    /// SPLIT: Egg Shuttle split filter
+    egg_shuttle_splits: EggShuttleSplits,
+    /// SPLIT: Split when the Park Token total is reached
+    park_token_split: ParkTokenSplit,
+    #[default = false]
+    /// SPLIT: Custom position triggers (set up as described in the README)
+    custom_splits: bool,
+    #[default = false]
+    /// Sonic Simulator - Split on each Chaos Emerald
+    sonic_simulator_emerald_split: bool,
+    #[default = false]
+    /// Sonic Simulator - Split only at the end of each world
+    sonic_simulator_world_splits: bool,
+    #[default = false]
+    /// Sonic Simulator - Final split requires all 7 Chaos Emeralds
+    sonic_simulator_all_emeralds: bool,
+    #[default = true]
     /// Tropical Resort - Act 1
     tropical_resort_1: bool,
     #[default = true]
@@ -168,6 +488,9 @@ struct Settings {
     #[default = true]
     /// Tropical Resort - BOSS
     tropical_resort_boss: bool,
+    #[default = false]
+    /// Tropical Resort - BOSS: split on final hit
+    tropical_resort_boss_final_hit: bool,
     #[default = true]
     /// Sweet Mountain - Act 1
     sweet_mountain_1: bool,
@@ -189,6 +512,9 @@ struct Settings {
     #[default = true]
     /// Sweet Mountain - BOSS
     sweet_mountain_boss: bool,
+    #[default = false]
+    /// Sweet Mountain - BOSS: split on final hit
+    sweet_mountain_boss_final_hit: bool,
     #[default = true]
     /// Startlight Carnival - Act 1
     starlight_carnival_1: bool,
@@ -210,6 +536,9 @@ struct Settings {
     #[default = true]
     /// Startlight Carnival - BOSS
     starlight_carnival_boss: bool,
+    #[default = false]
+    /// Starlight Carnival - BOSS: split on final hit
+    starlight_carnival_boss_final_hit: bool,
     #[default = true]
     /// Planet Wisp - Act 1
     planet_wisp_1: bool,
@@ -231,6 +560,9 @@ struct Settings {
     #[default = true]
     /// Planet Wisp - BOSS
     planet_wisp_boss: bool,
+    #[default = false]
+    /// Planet Wisp - BOSS: split on final hit
+    planet_wisp_boss_final_hit: bool,
     #[default = true]
     /// Aquarium Park - Act 1
     aquarium_park_1: bool,
@@ -252,6 +584,9 @@ struct Settings {
     #[default = true]
     /// Aquarium Park - BOSS
     aquarium_park_boss: bool,
+    #[default = false]
+    /// Aquarium Park - BOSS: split on final hit
+    aquarium_park_boss_final_hit: bool,
     #[default = true]
     /// Asteroid Coaster - Act 1
     asteroid_coaster_1: bool,
@@ -273,12 +608,18 @@ struct Settings {
     #[default = true]
     /// Asteroid Coaster - BOSS
     asteroid_coaster_boss: bool,
+    #[default = false]
+    /// Asteroid Coaster - BOSS: split on final hit
+    asteroid_coaster_boss_final_hit: bool,
     #[default = true]
     /// Terminal Velocity - Act 1
     terminal_velocity_1: bool,
     #[default = true]
     /// Terminal Velocity - BOSS
     terminal_velocity_boss: bool,
+    #[default = false]
+    /// Terminal Velocity - BOSS: split on final hit
+    terminal_velocity_boss_final_hit: bool,
     #[default = true]
     /// Terminal Velocity - Act 2
     terminal_velocity_2: bool,
@@ -345,361 +686,2217 @@ struct Settings {
     #[default = true]
     /// Sonic Simulator: 7 - 3
     sonic_simulator_7_3: bool,
+    #[default = true]
+    /// Rival Rush - Tropical Resort
+    rival_rush_tropical_resort: bool,
+    #[default = true]
+    /// Rival Rush - Sweet Mountain
+    rival_rush_sweet_mountain: bool,
+    #[default = true]
+    /// Rival Rush - Starlight Carnival
+    rival_rush_starlight_carnival: bool,
+    #[default = true]
+    /// Rival Rush - Planet Wisp
+    rival_rush_planet_wisp: bool,
+    #[default = true]
+    /// Rival Rush - Aquarium Park
+    rival_rush_aquarium_park: bool,
+    #[default = true]
+    /// Rival Rush - Asteroid Coaster
+    rival_rush_asteroid_coaster: bool,
+    #[default = false]
+    /// DEBUG: Log state transitions and start/split/reset decisions
+    debug_logging: bool,
+}
+
+impl Settings {
+    /// Whether splitting is enabled for the given level
+    fn level_split(&self, level: Levels) -> bool {
+        match level {
+            Levels::TropicalResortAct1 => self.tropical_resort_1,
+            Levels::TropicalResortAct2 => self.tropical_resort_2,
+            Levels::TropicalResortAct3 => self.tropical_resort_3,
+            Levels::TropicalResortAct4 => self.tropical_resort_4,
+            Levels::TropicalResortAct5 => self.tropical_resort_5,
+            Levels::TropicalResortAct6 => self.tropical_resort_6,
+            Levels::TropicalResortBoss => self.tropical_resort_boss,
+            Levels::SweetMountainAct1 => self.sweet_mountain_1,
+            Levels::SweetMountainAct2 => self.sweet_mountain_2,
+            Levels::SweetMountainAct3 => self.sweet_mountain_3,
+            Levels::SweetMountainAct4 => self.sweet_mountain_4,
+            Levels::SweetMountainAct5 => self.sweet_mountain_5,
+            Levels::SweetMountainAct6 => self.sweet_mountain_6,
+            Levels::SweetMountainBoss => self.sweet_mountain_boss,
+            Levels::StarlightCarnivalAct1 => self.starlight_carnival_1,
+            Levels::StarlightCarnivalAct2 => self.starlight_carnival_2,
+            Levels::StarlightCarnivalAct3 => self.starlight_carnival_3,
+            Levels::StarlightCarnivalAct4 => self.starlight_carnival_4,
+            Levels::StarlightCarnivalAct5 => self.starlight_carnival_5,
+            Levels::StarlightCarnivalAct6 => self.starlight_carnival_6,
+            Levels::StarlightCarnivalBoss => self.starlight_carnival_boss,
+            Levels::PlanetWispAct1 => self.planet_wisp_1,
+            Levels::PlanetWispAct2 => self.planet_wisp_2,
+            Levels::PlanetWispAct3 => self.planet_wisp_3,
+            Levels::PlanetWispAct4 => self.planet_wisp_4,
+            Levels::PlanetWispAct5 => self.planet_wisp_5,
+            Levels::PlanetWispAct6 => self.planet_wisp_6,
+            Levels::PlanetWispBoss => self.planet_wisp_boss,
+            Levels::AquariumParkAct1 => self.aquarium_park_1,
+            Levels::AquariumParkAct2 => self.aquarium_park_2,
+            Levels::AquariumParkAct3 => self.aquarium_park_3,
+            Levels::AquariumParkAct4 => self.aquarium_park_4,
+            Levels::AquariumParkAct5 => self.aquarium_park_5,
+            Levels::AquariumParkAct6 => self.aquarium_park_6,
+            Levels::AquariumParkBoss => self.aquarium_park_boss,
+            Levels::AsteroidCoasterAct1 => self.asteroid_coaster_1,
+            Levels::AsteroidCoasterAct2 => self.asteroid_coaster_2,
+            Levels::AsteroidCoasterAct3 => self.asteroid_coaster_3,
+            Levels::AsteroidCoasterAct4 => self.asteroid_coaster_4,
+            Levels::AsteroidCoasterAct5 => self.asteroid_coaster_5,
+            Levels::AsteroidCoasterAct6 => self.asteroid_coaster_6,
+            Levels::AsteroidCoasterBoss => self.asteroid_coaster_boss,
+            Levels::TerminalVelocityAct1 => self.terminal_velocity_1,
+            Levels::TerminalVelocityBoss => self.terminal_velocity_boss,
+            Levels::TerminalVelocityAct2 => self.terminal_velocity_2,
+            Levels::SonicSimulatorAct1_1 => self.sonic_simulator_1_1,
+            Levels::SonicSimulatorAct1_2 => self.sonic_simulator_1_2,
+            Levels::SonicSimulatorAct1_3 => self.sonic_simulator_1_3,
+            Levels::SonicSimulatorAct2_1 => self.sonic_simulator_2_1,
+            Levels::SonicSimulatorAct2_2 => self.sonic_simulator_2_2,
+            Levels::SonicSimulatorAct2_3 => self.sonic_simulator_2_3,
+            Levels::SonicSimulatorAct3_1 => self.sonic_simulator_3_1,
+            Levels::SonicSimulatorAct3_2 => self.sonic_simulator_3_2,
+            Levels::SonicSimulatorAct3_3 => self.sonic_simulator_3_3,
+            Levels::SonicSimulatorAct4_1 => self.sonic_simulator_4_1,
+            Levels::SonicSimulatorAct4_2 => self.sonic_simulator_4_2,
+            Levels::SonicSimulatorAct4_3 => self.sonic_simulator_4_3,
+            Levels::SonicSimulatorAct5_1 => self.sonic_simulator_5_1,
+            Levels::SonicSimulatorAct5_2 => self.sonic_simulator_5_2,
+            Levels::SonicSimulatorAct5_3 => self.sonic_simulator_5_3,
+            Levels::SonicSimulatorAct6_1 => self.sonic_simulator_6_1,
+            Levels::SonicSimulatorAct6_2 => self.sonic_simulator_6_2,
+            Levels::SonicSimulatorAct6_3 => self.sonic_simulator_6_3,
+            Levels::SonicSimulatorAct7_1 => self.sonic_simulator_7_1,
+            Levels::SonicSimulatorAct7_2 => self.sonic_simulator_7_2,
+            Levels::SonicSimulatorAct7_3 => self.sonic_simulator_7_3,
+            Levels::RivalRushTropicalResort => self.rival_rush_tropical_resort,
+            Levels::RivalRushSweetMountain => self.rival_rush_sweet_mountain,
+            Levels::RivalRushStarlightCarnival => self.rival_rush_starlight_carnival,
+            Levels::RivalRushPlanetWisp => self.rival_rush_planet_wisp,
+            Levels::RivalRushAquariumPark => self.rival_rush_aquarium_park,
+            Levels::RivalRushAsteroidCoaster => self.rival_rush_asteroid_coaster,
+            _ => false,
+        }
+    }
+
+    /// Whether the given boss splits on the final hit rather than at the end of the stage
+    fn boss_final_hit(&self, level: Levels) -> bool {
+        match level {
+            Levels::TropicalResortBoss => self.tropical_resort_boss_final_hit,
+            Levels::SweetMountainBoss => self.sweet_mountain_boss_final_hit,
+            Levels::StarlightCarnivalBoss => self.starlight_carnival_boss_final_hit,
+            Levels::PlanetWispBoss => self.planet_wisp_boss_final_hit,
+            Levels::AquariumParkBoss => self.aquarium_park_boss_final_hit,
+            Levels::AsteroidCoasterBoss => self.asteroid_coaster_boss_final_hit,
+            Levels::TerminalVelocityBoss => self.terminal_velocity_boss_final_hit,
+            _ => false,
+        }
+    }
 }
 
-fn update_loop(proc: &Process, addresses: &Addresses, watchers: &mut Watchers) {
-    let mut level: Levels = Levels::None;
-    let mut igt = Duration::ZERO;
-    let mut goal_ring = false;
-    let mut eggshuttle_progressiveid: u8 = 0;
-    let mut eggshuttle_totalstages: u8 = 0;
-    let mut runstart: u8 = 0;
-    let mut tr1rank: i8 = 0;
+/// Reads the game's memory into the watchers. Returns whether the pointer chain could be resolved
+/// down to the object holding the current level.
+fn update_loop(proc: &Process, addresses: &Addresses, watchers: &mut Watchers) -> bool {
+    let mut resolved = false;
+    // Set when a read fails on a non-null pointer, as opposed to there being nothing to read
+    let mut read_failed = false;
+    let mut read = Snapshot::default();
 
     if let Ok(addr_base) = proc.read::<Address64>(addresses.base_address) {
         if let Ok(addr_1) = proc.read::<Address64>(addr_base + 0x8) {
             if let Ok(addr_2) = proc.read::<Address64>(addr_1 + 0x38) {
+                // The level object below is null in menus, so the chain counts
+                // as resolved as soon as this pointer is valid
+                resolved = !addr_2.is_null();
+
                 if let Ok(addr_3) = proc.read::<Address64>(addr_2 + 0x60) {
                     let level_id = proc.read::<[u8; 6]>(addr_3 + 0xE0);
-                    level = match &level_id {
-                        Ok(x) => match x {
-                            b"stg110" => Levels::TropicalResortAct1,
-                            b"stg130" => Levels::TropicalResortAct2,
-                            b"stg120" => Levels::TropicalResortAct3,
-                            b"stg140" => Levels::TropicalResortAct4,
-                            b"stg150" => Levels::TropicalResortAct5,
-                            b"stg160" => Levels::TropicalResortAct6,
-                            b"stg190" => Levels::TropicalResortBoss,
-                            b"stg210" => Levels::SweetMountainAct1,
-                            b"stg230" => Levels::SweetMountainAct2,
-                            b"stg220" => Levels::SweetMountainAct3,
-                            b"stg260" => Levels::SweetMountainAct4,
-                            b"stg240" => Levels::SweetMountainAct5,
-                            b"stg250" => Levels::SweetMountainAct6,
-                            b"stg290" => Levels::SweetMountainBoss,
-                            b"stg310" => Levels::StarlightCarnivalAct1,
-                            b"stg330" => Levels::StarlightCarnivalAct2,
-                            b"stg340" => Levels::StarlightCarnivalAct3,
-                            b"stg350" => Levels::StarlightCarnivalAct4,
-                            b"stg320" => Levels::StarlightCarnivalAct5,
-                            b"stg360" => Levels::StarlightCarnivalAct6,
-                            b"stg390" => Levels::StarlightCarnivalBoss,
-                            b"stg410" => Levels::PlanetWispAct1,
-                            b"stg440" => Levels::PlanetWispAct2,
-                            b"stg450" => Levels::PlanetWispAct3,
-                            b"stg430" => Levels::PlanetWispAct4,
-                            b"stg460" => Levels::PlanetWispAct5,
-                            b"stg420" => Levels::PlanetWispAct6,
-                            b"stg490" => Levels::PlanetWispBoss,
-                            b"stg510" => Levels::AquariumParkAct1,
-                            b"stg540" => Levels::AquariumParkAct2,
-                            b"stg550" => Levels::AquariumParkAct3,
-                            b"stg530" => Levels::AquariumParkAct4,
-                            b"stg560" => Levels::AquariumParkAct5,
-                            b"stg520" => Levels::AquariumParkAct6,
-                            b"stg590" => Levels::AquariumParkBoss,
-                            b"stg610" => Levels::AsteroidCoasterAct1,
-                            b"stg630" => Levels::AsteroidCoasterAct2,
-                            b"stg640" => Levels::AsteroidCoasterAct3,
-                            b"stg650" => Levels::AsteroidCoasterAct4,
-                            b"stg660" => Levels::AsteroidCoasterAct5,
-                            b"stg620" => Levels::AsteroidCoasterAct6,
-                            b"stg690" => Levels::AsteroidCoasterBoss,
-                            b"stg710" => Levels::TerminalVelocityAct1,
-                            b"stg790" => Levels::TerminalVelocityBoss,
-                            b"stg720" => Levels::TerminalVelocityAct2,
-                            b"stgD10" => Levels::SonicSimulatorAct1_1,
-                            b"stgB20" => Levels::SonicSimulatorAct1_2,
-                            b"stgE50" => Levels::SonicSimulatorAct1_3,
-                            b"stgD20" => Levels::SonicSimulatorAct2_1,
-                            b"stgB30" => Levels::SonicSimulatorAct2_2,
-                            b"stgF30" => Levels::SonicSimulatorAct2_3,
-                            b"stgG10" => Levels::SonicSimulatorAct3_1,
-                            b"stgG30" => Levels::SonicSimulatorAct3_2,
-                            b"stgA10" => Levels::SonicSimulatorAct3_3,
-                            b"stgD30" => Levels::SonicSimulatorAct4_1,
-                            b"stgG20" => Levels::SonicSimulatorAct4_2,
-                            b"stgC50" => Levels::SonicSimulatorAct4_3,
-                            b"stgE30" => Levels::SonicSimulatorAct5_1,
-                            b"stgB10" => Levels::SonicSimulatorAct5_2,
-                            b"stgE40" => Levels::SonicSimulatorAct5_3,
-                            b"stgG40" => Levels::SonicSimulatorAct6_1,
-                            b"stgC40" => Levels::SonicSimulatorAct6_2,
-                            b"stgF40" => Levels::SonicSimulatorAct6_3,
-                            b"stgA30" => Levels::SonicSimulatorAct7_1,
-                            b"stgE20" => Levels::SonicSimulatorAct7_2,
-                            b"stgC10" => Levels::SonicSimulatorAct7_3,
-                            _ => Levels::None,
-                        },
+                    read_failed |= level_id.is_err() && !addr_3.is_null();
+                    read.level = match &level_id {
+                        Ok(x) => Levels::from_code(x),
                         _ => Levels::None,
                     };
 
-                    if level != Levels::None {
-                        igt = match proc.read::<f32>(addr_3 + 0x270) {
+                    if read.level != Levels::None {
+                        read.igt = match proc.read::<f32>(addr_3 + 0x270) {
                             Ok(x) => Duration::milliseconds((x * 100.0) as i64 * 10),
-                            _ => Duration::ZERO,
+                            _ => {
+                                read_failed = true;
+                                Duration::ZERO
+                            }
+                        };
+
+                        read.stageflags = match proc.read::<u8>(addr_3 + 0x110) {
+                            Ok(x) => StageFlags(x),
+                            _ => {
+                                read_failed = true;
+                                StageFlags::default()
+                            }
                         };
 
-                        goal_ring = match proc.read::<u8>(addr_3 + 0x110) {
-                            Ok(x) => (x & (1 << 5)) != 0,
+                        read.paused = match proc.read::<u8>(addr_3 + 0x118) {
+                            Ok(x) => x != 0,
                             _ => false,
                         };
+
+                        // Not confirmed yet: the active Color Power and, right after it, the capsules
+                        // collected so far in the act, one counter per Color Power in `Wisp::ALL` order
+                        if let Ok(player) = proc.read::<Address64>(addr_3 + 0x1B8) {
+                            if let Ok(x) = proc.read::<u8>(player + 0x3A0) {
+                                read.wisp = Wisp::from_id(x);
+                            }
+
+                            if let Ok(x) = proc.read::<[u8; Wisp::COUNT]>(player + 0x3A4) {
+                                read.wispcapsules = x;
+                            }
+                        }
+
+                        // The boss object comes and goes around cutscenes: keep the last values meanwhile
+                        if read.level.is_boss() {
+                            read.bosshealth = held(&watchers.bosshealth, 0);
+                            read.bossphase = held(&watchers.bossphase, 0);
+
+                            if let Ok(boss) = proc.read::<Address64>(addr_3 + 0x1C8) {
+                                if !boss.is_null() {
+                                    if let Ok(x) = proc.read::<u32>(boss + 0x150) {
+                                        read.bosshealth = x;
+                                    }
+                                    if let Ok(x) = proc.read::<u8>(boss + 0x154) {
+                                        read.bossphase = x;
+                                    }
+                                }
+                            }
+                        }
+
+                        if read.level.is_rival_rush() {
+                            read.rivalrush = match proc.read::<u8>(addr_3 + 0x2C4) {
+                                Ok(1) => RivalRushResult::Won,
+                                Ok(2) => RivalRushResult::Lost,
+                                _ => RivalRushResult::None,
+                            };
+                        }
                     }
+                } else {
+                    read_failed |= !addr_2.is_null();
                 }
 
                 if let Ok(addr_3) = proc.read::<Address64>(addr_2 + 0x68) {
                     if let Ok(addr_4) = proc.read::<Address64>(addr_3 + 0x110) {
                         if let Ok(sh) = proc.read::<u8>(addr_4) {
-                            eggshuttle_totalstages = sh;
+                            read.eggshuttle_totalstages = sh;
 
                             if let Ok(x) = proc.read::<u8>(addr_4 + 0xB8) {
-                                eggshuttle_progressiveid = x;
+                                read.eggshuttle_progressiveid = x;
                             }
+
+                            // Not confirmed yet: 1 is believed to be a game over and 2 a quit to the menu.
+                            // The reset logic falls back to the IGT when this can't be read.
+                            read.eggshuttle_state = match proc.read::<u8>(addr_4 + 0xC0) {
+                                Ok(1) => EggShuttleState::GameOver,
+                                Ok(2) => EggShuttleState::Quit,
+                                Ok(_) => EggShuttleState::Playing,
+                                _ => EggShuttleState::Unknown,
+                            };
+                        } else {
+                            read_failed |= !addr_4.is_null();
                         }
+                    } else {
+                        read_failed |= !addr_3.is_null();
                     }
+                } else {
+                    read_failed |= !addr_2.is_null();
                 }
+            } else {
+                read_failed |= !addr_1.is_null();
             }
 
             if let Ok(addr_2) = proc.read::<Address64>(addr_1 + 0x8) {
                 if let Ok(addr_3) = proc.read::<Address64>(addr_2 + 0x10) {
                     if let Ok(addr_4) = proc.read::<Address64>(addr_3 + 0x60) {
                         if let Ok(x) = proc.read::<u8>(addr_4 + 0x120) {
-                            runstart = x;
+                            read.runstart = x;
 
                             if let Ok(y) = proc.read::<i8>(addr_4 + 0x1CC) {
-                                tr1rank = y;
+                                read.tr1rank = y;
+                                read.tr1rank_read = true;
+                            }
+
+                            if let Ok(y) = proc.read::<u32>(addr_4 + 0x124) {
+                                read.parktokens = y;
+                            }
+
+                            // Customization unlocks, stored as one bit per item
+                            if let Ok(y) = proc.read::<u64>(addr_4 + 0x128) {
+                                read.unlocks = y.count_ones();
                             }
+
+                            // Sonic Simulator Chaos Emeralds, stored as one bit per world
+                            if let Ok(y) = proc.read::<u8>(addr_4 + 0x2A8) {
+                                read.chaosemeralds = (y & 0x7F).count_ones() as u8;
+                            }
+                        } else {
+                            read_failed |= !addr_4.is_null();
                         }
+                    } else {
+                        read_failed |= !addr_3.is_null();
                     }
+                } else {
+                    read_failed |= !addr_2.is_null();
                 }
+            } else {
+                read_failed |= !addr_1.is_null();
             }
+        } else {
+            read_failed |= !addr_base.is_null();
         }
+    } else {
+        read_failed = true;
     }
 
-    watchers.levelid.update_infallible(level);
-    watchers.igt.update_infallible(igt);
-    watchers.goalringreached.update_infallible(goal_ring);
-    watchers
-        .eggshuttle_progressiveid
-        .update_infallible(eggshuttle_progressiveid);
-    watchers
-        .eggshuttle_totalstages
-        .update_infallible(eggshuttle_totalstages);
-    watchers.runstart.update_infallible(runstart);
-    watchers.tr1rank.update_infallible(tr1rank);
+    if watchers.settleticks < SETTLE_TICKS {
+        watchers.settleticks = match resolved && !read_failed {
+            true => watchers.settleticks + 1,
+            _ => 0,
+        };
+    }
+
+    let mut snapshot = merge_snapshot(watchers.snapshot, read, read_failed);
+
+    // While an Egg Shuttle run is in progress, implausible counters (e.g. from a pointer
+    // that is transiently invalid during a stage transition) are discarded in favour of
+    // the last good values, so they never reach the split and reset logic
+    if watchers.currentgamemode == GameMode::EggShuttle && timer::state() != TimerState::NotRunning
+    {
+        if let (Some(totalstages), Some(progressiveid)) = (
+            &watchers.eggshuttle_totalstages.pair,
+            &watchers.eggshuttle_progressiveid.pair,
+        ) {
+            if !egg_shuttle_counters_plausible(
+                (totalstages.current, progressiveid.current),
+                (
+                    snapshot.eggshuttle_totalstages,
+                    snapshot.eggshuttle_progressiveid,
+                ),
+            ) {
+                snapshot.eggshuttle_totalstages = totalstages.current;
+                snapshot.eggshuttle_progressiveid = progressiveid.current;
+            }
+        }
+    }
+
+    update_watchers(watchers, snapshot);
+    publish_variables(watchers);
+
+    // Keeps track of whether the goal ring has been touched in the last level played,
+    // so a split can be deferred until the next level is loaded. Going to the world map
+    // or a menu in between doesn't count as loading a level.
+    let levelcleared = match (
+        &watchers.levelid.pair,
+        watchers.stage_flag(StageFlags::GOAL_RING),
+    ) {
+        (Some(levelid), _) if levelid.changed() && levelid.current != Levels::None => false,
+        (Some(levelid), Some(goalring)) if goalring.current && !goalring.old => {
+            watchers.clearedlevel = levelid.current;
+            watchers.clearedstage = held(&watchers.eggshuttle_progressiveid, 0);
+            true
+        }
+        _ => watchers.levelcleared.pair.is_some_and(|x| x.current),
+    };
+    watchers.levelcleared.update_infallible(levelcleared);
 
     if timer::state() == TimerState::NotRunning {
         if watchers.accumulatedigt != Duration::ZERO {
             watchers.accumulatedigt = Duration::ZERO
         }
+        watchers.bosslatch = None;
 
-        if let Some(eggshuttlecount) = &watchers.eggshuttle_totalstages.pair {
-            watchers.currentgamemode =
-                match eggshuttlecount.current > 0 && eggshuttlecount.current <= 45 {
-                    true => GameMode::EggShuttle,
-                    _ => GameMode::AnyPercent,
-                };
+        // Runs that end with a reset get their statistics printed here, as they're cleared
+        if !watchers.runlog.is_empty() {
+            if !watchers.runended {
+                watchers.runlog.print();
+            }
+            watchers.runlog.clear();
+        }
+
+        watchers.runended = false;
+        watchers.resetpending = false;
+        watchers.parktokensreached = false;
+        watchers.queuedsplits = 0;
+        watchers.gametimeunknown = false;
+
+        if watchers.eggshuttle_totalstages.pair.is_some() {
+            watchers.currentgamemode = detect_game_mode(watchers);
         }
+
+        watchers.parktokens_runstart = snapshot.parktokens;
+        watchers.unlocks_runstart = snapshot.unlocks;
+        watchers.wispuses = [0; Wisp::COUNT];
+    } else {
+        update_run_log(watchers);
+
+        if let Some(wisp) = &watchers.wisp.pair {
+            if wisp.changed() && wisp.current != Wisp::None {
+                let uses = &mut watchers.wispuses[wisp.current as usize - 1];
+                *uses = uses.saturating_add(1);
+                publish_wisp_uses(&watchers.wispuses);
+            }
+        }
+
+        timer::set_variable_int(
+            "Park Tokens (run)",
+            snapshot
+                .parktokens
+                .saturating_sub(watchers.parktokens_runstart),
+        );
+        timer::set_variable_int(
+            "Unlocks (run)",
+            snapshot.unlocks.saturating_sub(watchers.unlocks_runstart),
+        );
     }
 
-    if let Some(igtpair) = &watchers.igt.pair {
-        if igtpair.old != Duration::ZERO && igtpair.current == Duration::ZERO {
-            watchers.accumulatedigt += igtpair.old
+    // Entering an act or restarting it re-arms its custom split triggers. This is done here
+    // rather than in `custom_split`, which doesn't run on updates where another split fired.
+    if let (Some(levelid), Some(igt)) = (&watchers.levelid.pair, &watchers.igt.pair) {
+        if levelid.changed() || (igt.old != Duration::ZERO && igt.current == Duration::ZERO) {
+            watchers.customsplits.fired = 0;
         }
     }
-}
 
-fn start(watchers: &Watchers, settings: &Settings) -> bool {
-    let Some(level_id) = &watchers.levelid.pair else {
-        return false;
-    };
-    let Some(igt) = &watchers.igt.pair else {
-        return false;
+    // Definitions kept in the settings map are only looked at once in a while
+    if watchers.definitionticks == 0 {
+        watchers.customsplits.reload();
+        watchers.eggshuttlestages = parse_stage_list(
+            &settings::Map::load()
+                .get(EGG_SHUTTLE_STAGES_KEY)
+                .and_then(|value| value.get_array_string::<256>())
+                .and_then(Result::ok)
+                .unwrap_or_default(),
+        );
+    }
+    watchers.definitionticks = (watchers.definitionticks + 1) % DEFINITIONS_RELOAD_TICKS;
+
+    watchers.igtstillticks = match watchers.igt.pair {
+        Some(igt) if igt.current > igt.old => 0,
+        _ => watchers.igtstillticks.saturating_add(1),
     };
-    let Some(runstart) = &watchers.runstart.pair else {
+
+    accumulate_igt(watchers);
+
+    resolved
+}
+
+/// Everything read from the game's memory in one update
+#[derive(Clone, Copy, Default)]
+struct Snapshot {
+    level: Levels,
+    igt: Duration,
+    stageflags: StageFlags,
+    paused: bool,
+    wisp: Wisp,
+    wispcapsules: [u8; Wisp::COUNT],
+    bosshealth: u32,
+    bossphase: u8,
+    eggshuttle_totalstages: u8,
+    eggshuttle_progressiveid: u8,
+    eggshuttle_state: EggShuttleState,
+    runstart: u8,
+    tr1rank: i8,
+    /// Whether `tr1rank` comes from the save data, rather than being a default
+    tr1rank_read: bool,
+    chaosemeralds: u8,
+    rivalrush: RivalRushResult,
+    parktokens: u32,
+    unlocks: u32,
+}
+
+/// A failed read is not the same as a real zero: hold on to the last good snapshot
+/// until the game's memory can be read again, so a failure never looks like a level
+/// change or an IGT reset
+fn merge_snapshot(last: Option<Snapshot>, read: Snapshot, read_failed: bool) -> Snapshot {
+    match (read_failed, last) {
+        (true, Some(last)) => last,
+        _ => read,
+    }
+}
+
+fn update_watchers(watchers: &mut Watchers, snapshot: Snapshot) {
+    watchers.snapshot = Some(snapshot);
+    watchers.levelid.update_infallible(snapshot.level);
+    watchers.igt.update_infallible(snapshot.igt);
+    watchers.stageflags.update_infallible(snapshot.stageflags);
+    watchers.paused.update_infallible(snapshot.paused);
+    watchers
+        .eggshuttle_progressiveid
+        .update_infallible(snapshot.eggshuttle_progressiveid);
+    watchers
+        .eggshuttle_totalstages
+        .update_infallible(snapshot.eggshuttle_totalstages);
+    watchers
+        .eggshuttle_state
+        .update_infallible(snapshot.eggshuttle_state);
+    watchers.runstart.update_infallible(snapshot.runstart);
+    watchers.tr1rank.update_infallible(snapshot.tr1rank);
+    watchers
+        .tr1rank_read
+        .update_infallible(snapshot.tr1rank_read);
+    watchers
+        .chaosemeralds
+        .update_infallible(snapshot.chaosemeralds);
+    watchers.rivalrush.update_infallible(snapshot.rivalrush);
+    watchers.wisp.update_infallible(snapshot.wisp);
+    watchers
+        .wispcapsules
+        .update_infallible(snapshot.wispcapsules);
+    watchers.bosshealth.update_infallible(snapshot.bosshealth);
+    watchers.bossphase.update_infallible(snapshot.bossphase);
+    watchers.parktokens.update_infallible(snapshot.parktokens);
+    watchers.unlocks.update_infallible(snapshot.unlocks);
+}
+
+/// Publishes the values that changed in the last update as timer variables
+fn publish_variables(watchers: &Watchers) {
+    if let Some(stageflags) = watchers.stageflags.pair.filter(|x| x.changed()) {
+        let mut flags = ArrayString::<64>::new();
+        let _ = write!(flags, "{}", stageflags.current);
+        timer::set_variable("Stage flags", &flags);
+    }
+    if let Some(paused) = watchers.paused.pair.filter(|x| x.changed()) {
+        timer::set_variable("Paused", if paused.current { "Yes" } else { "No" });
+    }
+    if let Some(progressiveid) = watchers
+        .eggshuttle_progressiveid
+        .pair
+        .filter(|x| x.changed())
+    {
+        if watchers.currentgamemode == GameMode::EggShuttle {
+            timer::set_variable_int("Egg Shuttle Stage", progressiveid.current as u32 + 1);
+        }
+    }
+    if let Some(wisp) = watchers.wisp.pair.filter(|x| x.changed()) {
+        timer::set_variable("Color Power", wisp.current.name());
+    }
+    if let Some(wispcapsules) = watchers.wispcapsules.pair.filter(|x| x.changed()) {
+        publish_wisp_counts("Wisp capsules", wispcapsules.current.map(u32::from));
+    }
+    if let Some(bosshealth) = watchers.bosshealth.pair.filter(|x| x.changed()) {
+        timer::set_variable_int("Boss health", bosshealth.current);
+    }
+    if let Some(bossphase) = watchers.bossphase.pair.filter(|x| x.changed()) {
+        timer::set_variable_int("Boss phase", bossphase.current);
+    }
+    if let Some(parktokens) = watchers.parktokens.pair.filter(|x| x.changed()) {
+        timer::set_variable_int("Park Tokens", parktokens.current);
+    }
+    if let Some(unlocks) = watchers.unlocks.pair.filter(|x| x.changed()) {
+        timer::set_variable_int("Unlocks", unlocks.current);
+    }
+}
+
+/// Adds the IGT of the act that was just left or restarted to the run total.
+/// Returns whether anything was added.
+fn accumulate_igt(watchers: &mut Watchers) -> bool {
+    let Some(igtpair) = &watchers.igt.pair else {
         return false;
     };
-    let Some(tr1rank) = &watchers.tr1rank.pair else {
+
+    if igtpair.old == Duration::ZERO || igtpair.current != Duration::ZERO {
         return false;
+    }
+
+    // A boss that split on the final hit only counts up to that hit
+    watchers.accumulatedigt += match watchers.bosslatch.take() {
+        Some(latch) => latch.min(igtpair.old),
+        _ => igtpair.old,
     };
+    true
+}
 
-    match watchers.currentgamemode {
-        GameMode::EggShuttle => {
-            settings.start_egg_shuttle
-                && level_id.current == Levels::TropicalResortAct1
-                && (level_id.old == Levels::None
-                    || (igt.old > asr::time::Duration::ZERO
-                        && igt.current == asr::time::Duration::ZERO))
+/// Works out the game mode from what the game is currently showing
+fn detect_game_mode(watchers: &Watchers) -> GameMode {
+    let totalstages = held(&watchers.eggshuttle_totalstages, 0);
+
+    match totalstages > 0 && totalstages <= 45 {
+        true => GameMode::EggShuttle,
+        _ if held(&watchers.levelid, Levels::None).is_sonic_simulator() => GameMode::SonicSimulator,
+        _ => GameMode::AnyPercent,
+    }
+}
+
+/// Rebuilds the run state when the auto splitter is loaded while the timer is already running,
+/// e.g. after a layout change. The game mode comes from the game's memory, while the time
+/// accumulated over the previous acts comes from the copy kept in the settings map.
+fn resume_run(watchers: &mut Watchers, settings: &Settings) {
+    let split_index = timer::current_split_index().unwrap_or_default();
+    watchers.currentgamemode = detect_game_mode(watchers);
+    watchers.runended = timer::state() == TimerState::Ended;
+
+    // The saved state is cleared on reset, and only trusted if it can belong to this run
+    match SavedRun::load().filter(|saved| {
+        saved.game_mode == watchers.currentgamemode && saved.split_index <= split_index
+    }) {
+        Some(saved) => {
+            watchers.accumulatedigt = saved.accumulated_igt;
+            watchers.startoffset = saved.start_offset;
+            watchers.lastgametime = saved.game_time;
+            watchers.savedrun = Some(saved);
         }
         _ => {
-            (settings.start_anypercent
-                && tr1rank.current == -1
-                && runstart.old == 35
-                && runstart.current == 110)
-                || (settings.start_sonic_simulator
-                    && level_id.current == Levels::SonicSimulatorAct1_1
-                    && level_id.old == Levels::None)
+            watchers.gametimeunknown = true;
+            SavedRun::clear(watchers);
+        }
+    }
+
+    // Rebuild the position in the route: the act being played, and whether its goal ring
+    // has been reached already. The statistics of the previous acts are lost.
+    watchers.runlog.clear();
+    if let Some(levelid) = &watchers.levelid.pair {
+        if levelid.current != Levels::None {
+            watchers.runlog.enter(levelid.current);
         }
     }
+    let goalring = watchers
+        .stage_flag(StageFlags::GOAL_RING)
+        .is_some_and(|x| x.current);
+    watchers.levelcleared.update_infallible(goalring);
+    watchers.levelcleared.update_infallible(goalring);
+    watchers.clearedlevel = held(&watchers.levelid, Levels::None);
+    watchers.clearedstage = held(&watchers.eggshuttle_progressiveid, 0);
+
+    match watchers.gametimeunknown {
+        true => debug_print(
+            settings,
+            format_args!("resumed run: split {split_index}, no saved state for this run"),
+        ),
+        _ => debug_print(
+            settings,
+            format_args!(
+                "resumed run: split {}, game time {}",
+                split_index,
+                FormattedTime(watchers.lastgametime)
+            ),
+        ),
+    }
 }
 
-fn split(watchers: &Watchers, settings: &Settings) -> bool {
-    let Some(levelid) = &watchers.levelid.pair else {
-        return false;
-    };
+/// Logs every transition the start, split and reset logic relies on, when debug logging is enabled
+fn log_edges(watchers: &mut Watchers, settings: &Settings) {
+    let mut edges = false;
 
-    let cont = match levelid.old {
-        Levels::TropicalResortAct1 => settings.tropical_resort_1,
-        Levels::TropicalResortAct2 => settings.tropical_resort_2,
-        Levels::TropicalResortAct3 => settings.tropical_resort_3,
-        Levels::TropicalResortAct4 => settings.tropical_resort_4,
-        Levels::TropicalResortAct5 => settings.tropical_resort_5,
-        Levels::TropicalResortAct6 => settings.tropical_resort_6,
-        Levels::TropicalResortBoss => settings.tropical_resort_boss,
-        Levels::SweetMountainAct1 => settings.sweet_mountain_1,
-        Levels::SweetMountainAct2 => settings.sweet_mountain_2,
-        Levels::SweetMountainAct3 => settings.sweet_mountain_3,
-        Levels::SweetMountainAct4 => settings.sweet_mountain_4,
-        Levels::SweetMountainAct5 => settings.sweet_mountain_5,
-        Levels::SweetMountainAct6 => settings.sweet_mountain_6,
-        Levels::SweetMountainBoss => settings.sweet_mountain_boss,
-        Levels::StarlightCarnivalAct1 => settings.starlight_carnival_1,
-        Levels::StarlightCarnivalAct2 => settings.starlight_carnival_2,
-        Levels::StarlightCarnivalAct3 => settings.starlight_carnival_3,
-        Levels::StarlightCarnivalAct4 => settings.starlight_carnival_4,
-        Levels::StarlightCarnivalAct5 => settings.starlight_carnival_5,
-        Levels::StarlightCarnivalAct6 => settings.starlight_carnival_6,
-        Levels::StarlightCarnivalBoss => settings.starlight_carnival_boss,
-        Levels::PlanetWispAct1 => settings.planet_wisp_1,
-        Levels::PlanetWispAct2 => settings.planet_wisp_2,
-        Levels::PlanetWispAct3 => settings.planet_wisp_3,
-        Levels::PlanetWispAct4 => settings.planet_wisp_4,
-        Levels::PlanetWispAct5 => settings.planet_wisp_5,
-        Levels::PlanetWispAct6 => settings.planet_wisp_6,
-        Levels::PlanetWispBoss => settings.planet_wisp_boss,
-        Levels::AquariumParkAct1 => settings.aquarium_park_1,
-        Levels::AquariumParkAct2 => settings.aquarium_park_2,
-        Levels::AquariumParkAct3 => settings.aquarium_park_3,
-        Levels::AquariumParkAct4 => settings.aquarium_park_4,
-        Levels::AquariumParkAct5 => settings.aquarium_park_5,
-        Levels::AquariumParkAct6 => settings.aquarium_park_6,
-        Levels::AquariumParkBoss => settings.aquarium_park_boss,
-        Levels::AsteroidCoasterAct1 => settings.asteroid_coaster_1,
-        Levels::AsteroidCoasterAct2 => settings.asteroid_coaster_2,
-        Levels::AsteroidCoasterAct3 => settings.asteroid_coaster_3,
-        Levels::AsteroidCoasterAct4 => settings.asteroid_coaster_4,
-        Levels::AsteroidCoasterAct5 => settings.asteroid_coaster_5,
-        Levels::AsteroidCoasterAct6 => settings.asteroid_coaster_6,
-        Levels::AsteroidCoasterBoss => settings.asteroid_coaster_boss,
-        Levels::TerminalVelocityAct1 => settings.terminal_velocity_1,
-        Levels::TerminalVelocityBoss => settings.terminal_velocity_boss,
-        Levels::TerminalVelocityAct2 => settings.terminal_velocity_2,
-        Levels::SonicSimulatorAct1_1 => settings.sonic_simulator_1_1,
-        Levels::SonicSimulatorAct1_2 => settings.sonic_simulator_1_2,
-        Levels::SonicSimulatorAct1_3 => settings.sonic_simulator_1_3,
-        Levels::SonicSimulatorAct2_1 => settings.sonic_simulator_2_1,
-        Levels::SonicSimulatorAct2_2 => settings.sonic_simulator_2_2,
-        Levels::SonicSimulatorAct2_3 => settings.sonic_simulator_2_3,
-        Levels::SonicSimulatorAct3_1 => settings.sonic_simulator_3_1,
-        Levels::SonicSimulatorAct3_2 => settings.sonic_simulator_3_2,
-        Levels::SonicSimulatorAct3_3 => settings.sonic_simulator_3_3,
-        Levels::SonicSimulatorAct4_1 => settings.sonic_simulator_4_1,
-        Levels::SonicSimulatorAct4_2 => settings.sonic_simulator_4_2,
-        Levels::SonicSimulatorAct4_3 => settings.sonic_simulator_4_3,
-        Levels::SonicSimulatorAct5_1 => settings.sonic_simulator_5_1,
-        Levels::SonicSimulatorAct5_2 => settings.sonic_simulator_5_2,
-        Levels::SonicSimulatorAct5_3 => settings.sonic_simulator_5_3,
-        Levels::SonicSimulatorAct6_1 => settings.sonic_simulator_6_1,
-        Levels::SonicSimulatorAct6_2 => settings.sonic_simulator_6_2,
-        Levels::SonicSimulatorAct6_3 => settings.sonic_simulator_6_3,
-        Levels::SonicSimulatorAct7_1 => settings.sonic_simulator_7_1,
-        Levels::SonicSimulatorAct7_2 => settings.sonic_simulator_7_2,
-        Levels::SonicSimulatorAct7_3 => settings.sonic_simulator_7_3,
-        _ => false,
-    };
+    if let Some(levelid) = &watchers.levelid.pair {
+        if levelid.changed() {
+            edges = true;
+            debug_print(
+                settings,
+                format_args!(
+                    "level: {} -> {}",
+                    levelid.old.name(),
+                    levelid.current.name()
+                ),
+            );
+        }
+    }
 
-    if !cont {
-        return false;
+    if let Some(stageflags) = &watchers.stageflags.pair {
+        if stageflags.changed() {
+            edges = true;
+            debug_print(
+                settings,
+                format_args!("stage flags: {} -> {}", stageflags.old, stageflags.current),
+            );
+        }
     }
 
-    let Some(goalringreached) = &watchers.goalringreached.pair else {
-        return false;
-    };
+    if let Some(igt) = &watchers.igt.pair {
+        if igt.old != Duration::ZERO && igt.current == Duration::ZERO {
+            edges = true;
+            debug_print(
+                settings,
+                format_args!("IGT reset (was {})", FormattedTime(igt.old)),
+            );
+        }
+    }
 
-    if watchers.currentgamemode == GameMode::EggShuttle {
-        let Some(progressiveid) = &watchers.eggshuttle_progressiveid.pair else {
-            return false;
-        };
-        let Some(totalstages) = &watchers.eggshuttle_totalstages.pair else {
-            return false;
-        };
+    if let Some(runstart) = &watchers.runstart.pair {
+        if runstart.changed() {
+            edges = true;
+            debug_print(
+                settings,
+                format_args!("runstart: {} -> {}", runstart.old, runstart.current),
+            );
+        }
+    }
 
-        if progressiveid.old == totalstages.current - 1 {
-            goalringreached.current && !goalringreached.old
-        } else {
-            progressiveid.current == progressiveid.old + 1
+    if let (Some(totalstages), Some(progressiveid)) = (
+        &watchers.eggshuttle_totalstages.pair,
+        &watchers.eggshuttle_progressiveid.pair,
+    ) {
+        if totalstages.changed() || progressiveid.changed() {
+            edges = true;
+            debug_print(
+                settings,
+                format_args!(
+                    "Egg Shuttle: stage {}/{} -> {}/{}",
+                    progressiveid.old, totalstages.old, progressiveid.current, totalstages.current
+                ),
+            );
         }
-    } else if levelid.old == Levels::TerminalVelocityAct2 {
-        goalringreached.current && !goalringreached.old
-    } else {
-        !goalringreached.current && goalringreached.old
     }
+
+    watchers.edges = edges;
 }
 
-fn reset(watchers: &Watchers, settings: &Settings) -> bool {
-    if watchers.currentgamemode == GameMode::EggShuttle {
-        let Some(igt) = &watchers.igt.pair else {
-            return false;
-        };
-        let Some(goal_ring) = &watchers.goalringreached.pair else {
-            return false;
-        };
-        settings.reset_egg_shuttle
-            && igt.old != Duration::ZERO
-            && igt.current == Duration::ZERO
-            && !goal_ring.old
-    } else {
-        let Some(runstart) = &watchers.runstart.pair else {
-            return false;
-        };
-        settings.reset_anypercent && runstart.old == 110 && runstart.current == 35
+fn debug_print(settings: &Settings, message: fmt::Arguments<'_>) {
+    if settings.debug_logging {
+        asr::print_limited::<128>(&format_args!("[debug] {message}"));
     }
 }
 
-fn is_loading(_watchers: &Watchers, _settings: &Settings) -> Option<bool> {
-    Some(true)
+/// Logs a start, split or reset decision, but only on updates where something changed
+fn debug_log(watchers: &Watchers, settings: &Settings, message: fmt::Arguments<'_>) {
+    if watchers.edges {
+        debug_print(settings, message);
+    }
 }
 
-fn game_time(watchers: &Watchers, _settings: &Settings) -> Option<Duration> {
-    Some(watchers.igt.pair?.current + watchers.accumulatedigt)
+/// Logs why an action did not fire and returns `false`
+fn rejected(watchers: &Watchers, settings: &Settings, action: &str, reason: &str) -> bool {
+    debug_log(
+        watchers,
+        settings,
+        format_args!("{action}: not fired ({reason})"),
+    );
+    false
 }
 
-#[derive(Clone, Copy, PartialEq, Default)]
-enum GameMode {
-    #[default]
-    AnyPercent,
-    EggShuttle,
+/// Publishes Sonic's speed, position and boost gauge as variables, at the rate chosen in the settings
+fn update_telemetry(
+    proc: &Process,
+    addresses: &Addresses,
+    watchers: &mut Watchers,
+    settings: &Settings,
+) {
+    let Some(interval) = settings.telemetry_rate.interval_ticks() else {
+        return;
+    };
+
+    watchers.telemetryticks += 1;
+    if watchers.telemetryticks < interval {
+        return;
+    }
+    watchers.telemetryticks = 0;
+
+    let Some(player) = player_address(proc, addresses) else {
+        return;
+    };
+
+    let mut value = ArrayString::<64>::new();
+
+    if let Ok([x, y, z]) = proc.read::<[f32; 3]>(player + 0xA0) {
+        let _ = write!(value, "{:.1}", sqrt(x * x + y * y + z * z));
+        timer::set_variable("Speed", &value);
+    }
+
+    if let Ok([x, y, z]) = proc.read::<[f32; 3]>(player + 0x80) {
+        value.clear();
+        let _ = write!(value, "{x:.1}, {y:.1}, {z:.1}");
+        timer::set_variable("Position", &value);
+    }
+
+    if let Ok(boost) = proc.read::<f32>(player + 0x2F4) {
+        value.clear();
+        let _ = write!(value, "{:.0}%", boost.clamp(0.0, 1.0) * 100.0);
+        timer::set_variable("Boost", &value);
+    }
 }
 
-#[derive(Clone, Copy, PartialEq)]
-enum Levels {
-    TropicalResortAct1,
-    TropicalResortAct2,
-    TropicalResortAct3,
-    TropicalResortAct4,
-    TropicalResortAct5,
-    TropicalResortAct6,
-    TropicalResortBoss,
-    SweetMountainAct1,
-    SweetMountainAct2,
-    SweetMountainAct3,
-    SweetMountainAct4,
-    SweetMountainAct5,
-    SweetMountainAct6,
-    SweetMountainBoss,
-    StarlightCarnivalAct1,
-    StarlightCarnivalAct2,
-    StarlightCarnivalAct3,
+/// Resolves the player object of the current stage, if there is one
+fn player_address(proc: &Process, addresses: &Addresses) -> Option<Address64> {
+    let addr_base = proc.read::<Address64>(addresses.base_address).ok()?;
+    let addr_1 = proc.read::<Address64>(addr_base + 0x8).ok()?;
+    let addr_2 = proc.read::<Address64>(addr_1 + 0x38).ok()?;
+    let addr_3 = proc.read::<Address64>(addr_2 + 0x60).ok()?;
+    let player = proc.read::<Address64>(addr_3 + 0x1B8).ok()?;
+
+    match player.is_null() {
+        true => None,
+        _ => Some(player),
+    }
+}
+
+/// Splits on the runner's own triggers: Sonic entering a trigger box. Each trigger fires
+/// once per act attempt.
+fn custom_split(
+    proc: &Process,
+    addresses: &Addresses,
+    watchers: &mut Watchers,
+    settings: &Settings,
+) -> bool {
+    if !settings.custom_splits {
+        return false;
+    }
+
+    let Some(levelid) = &watchers.levelid.pair else {
+        return false;
+    };
+    let customsplits = &mut watchers.customsplits;
+
+    if !customsplits
+        .triggers
+        .iter()
+        .any(|trigger| trigger.level == levelid.current)
+    {
+        return false;
+    }
+
+    let Some(position) = player_address(proc, addresses)
+        .and_then(|player| proc.read::<[f32; 3]>(player + 0x80).ok())
+    else {
+        return false;
+    };
+
+    for (i, trigger) in customsplits.triggers.iter().enumerate() {
+        if trigger.level == levelid.current
+            && customsplits.fired & (1 << i) == 0
+            && trigger.contains(position)
+        {
+            customsplits.fired |= 1 << i;
+            debug_print(
+                settings,
+                format_args!(
+                    "split: fired (custom trigger \"{}\")",
+                    trigger.label.as_str()
+                ),
+            );
+            return true;
+        }
+    }
+
+    false
+}
+
+/// Square root by Newton's method, as `f32::sqrt` isn't available without `std`
+fn sqrt(x: f32) -> f32 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+
+    let mut root = x.max(1.0);
+    for _ in 0..24 {
+        root = 0.5 * (root + x / root);
+    }
+    root
+}
+
+/// Whether the boss of the current stage has just taken its final hit
+fn final_hit(watchers: &Watchers) -> bool {
+    let (Some(levelid), Some(bosshealth)) = (&watchers.levelid.pair, &watchers.bosshealth.pair)
+    else {
+        return false;
+    };
+
+    levelid.current.is_boss()
+        && !levelid.changed()
+        && bosshealth.old != 0
+        && bosshealth.current == 0
+}
+
+/// Stops the act IGT at the final hit on bosses that split on it
+fn latch_final_hit(watchers: &mut Watchers, settings: &Settings) {
+    let Some(levelid) = &watchers.levelid.pair else {
+        return;
+    };
+
+    if settings.boss_final_hit(levelid.current) && final_hit(watchers) {
+        watchers.bosslatch = watchers.igt.pair.map(|igt| igt.current);
+    }
+}
+
+/// Publishes the Color Power activations of the current run, e.g. `Laser 2, Rocket 1`
+fn publish_wisp_uses(wispuses: &[u16; Wisp::COUNT]) {
+    publish_wisp_counts("Wisp uses", wispuses.map(u32::from));
+}
+
+/// Publishes a count per Color Power, leaving out the ones at zero
+fn publish_wisp_counts(key: &str, counts: [u32; Wisp::COUNT]) {
+    let mut value = ArrayString::<128>::new();
+
+    for (wisp, count) in Wisp::ALL.iter().zip(counts) {
+        if count != 0 {
+            let separator = if value.is_empty() { "" } else { ", " };
+            let _ = write!(value, "{separator}{} {count}", wisp.name());
+        }
+    }
+
+    timer::set_variable(key, if value.is_empty() { "None" } else { &value });
+}
+
+/// The last value seen by a watcher, or the given default if it has never been updated
+fn held<T: Copy>(watcher: &Watcher<T>, default: T) -> T {
+    watcher.pair.as_ref().map_or(default, |x| x.current)
+}
+
+fn update_run_log(watchers: &mut Watchers) {
+    let Some(levelid) = &watchers.levelid.pair else {
+        return;
+    };
+    let Some(igt) = &watchers.igt.pair else {
+        return;
+    };
+    let Some(goalringreached) = watchers.stage_flag(StageFlags::GOAL_RING) else {
+        return;
+    };
+
+    if levelid.current != Levels::None {
+        watchers.runlog.enter(levelid.current);
+    }
+
+    // Restarting from the pause menu resets the IGT without the goal ring being reached
+    let restarted = levelid.current == levelid.old
+        && igt.old != Duration::ZERO
+        && igt.current == Duration::ZERO
+        && !goalringreached.old;
+
+    if restarted {
+        watchers.runlog.record_restart(levelid.current);
+    }
+
+    let rivalrush_won = watchers
+        .rivalrush
+        .pair
+        .is_some_and(|x| x.current == RivalRushResult::Won && x.old != RivalRushResult::Won);
+
+    if (goalringreached.current && !goalringreached.old) || rivalrush_won {
+        watchers
+            .runlog
+            .record_completion(levelid.current, igt.current);
+        timer::set_variable("Run Summary", &watchers.runlog.to_json());
+    }
+
+    if restarted || levelid.changed() {
+        let act_restarts = watchers
+            .runlog
+            .current_act(levelid.current)
+            .map_or(0, |act| act.restarts);
+        timer::set_variable_int("Restarts", watchers.runlog.restarts);
+        timer::set_variable_int("Restarts (act)", act_restarts);
+    }
+}
+
+fn start(watchers: &Watchers, settings: &Settings) -> bool {
+    let Some(level_id) = &watchers.levelid.pair else {
+        return false;
+    };
+    let Some(igt) = &watchers.igt.pair else {
+        return false;
+    };
+    let Some(runstart) = &watchers.runstart.pair else {
+        return false;
+    };
+    let Some(tr1rank) = &watchers.tr1rank.pair else {
+        return false;
+    };
+    let Some(tr1rank_read) = &watchers.tr1rank_read.pair else {
+        return false;
+    };
+
+    let fired = match watchers.currentgamemode {
+        GameMode::EggShuttle => {
+            settings.start_egg_shuttle
+                && level_id.current == Levels::TropicalResortAct1
+                && (level_id.old == Levels::None
+                    || (igt.old > asr::time::Duration::ZERO
+                        && igt.current == asr::time::Duration::ZERO))
+        }
+        _ => match settings.category.first_level() {
+            Some(first_level) => {
+                settings.start_category
+                    && level_id.current == first_level
+                    && level_id.old != first_level
+            }
+            _ => {
+                (settings.start_anypercent
+                    && (settings.start_anypercent_existing_save || tr1rank.current == -1)
+                    && match settings.start_anypercent_event {
+                        // A freshly created save has no rank recorded for Tropical Resort Act 1.
+                        // Both values have to come from the save data: the save data becoming
+                        // readable (e.g. on the title screen) is not a save being created.
+                        AnyPercentStart::NewSaveCreated => {
+                            tr1rank_read.old
+                                && tr1rank_read.current
+                                && tr1rank.old != -1
+                                && tr1rank.current == -1
+                        }
+                        AnyPercentStart::SaveSlotConfirmed => {
+                            runstart.old == 35 && runstart.current == 110
+                        }
+                        AnyPercentStart::FirstControl => {
+                            level_id.current == Levels::TropicalResortAct1
+                                && igt.old == Duration::ZERO
+                                && igt.current > Duration::ZERO
+                        }
+                    })
+                    || (settings.start_sonic_simulator
+                        && (level_id.current == Levels::SonicSimulatorAct1_1
+                            || (settings.start_sonic_simulator_any_act
+                                && level_id.current.is_sonic_simulator()))
+                        && level_id.old == Levels::None)
+            }
+        },
+    };
+
+    if !fired {
+        let reason = match watchers.currentgamemode {
+            GameMode::EggShuttle if !settings.start_egg_shuttle => {
+                "Egg Shuttle auto start disabled"
+            }
+            GameMode::EggShuttle => "waiting for Tropical Resort - Act 1 to load or restart",
+            _ if settings.category != Category::FullGame => "waiting for entry to the first level",
+            _ => "Any% / Sonic Simulator start conditions not met",
+        };
+        return rejected(watchers, settings, "start", reason);
+    }
+
+    true
+}
+
+/// Whether the Park Token total has just reached the threshold in the settings. Tokens are
+/// spent on unlocks and earned back, so this only counts once per run.
+fn park_tokens_reached(watchers: &Watchers, settings: &Settings) -> bool {
+    let (Some(threshold), Some(parktokens)) = (
+        settings.park_token_split.threshold(),
+        &watchers.parktokens.pair,
+    ) else {
+        return false;
+    };
+
+    !watchers.parktokensreached && parktokens.old < threshold && parktokens.current >= threshold
+}
+
+/// Whether a Chaos Emerald was just collected in Sonic Simulator, with emerald splits enabled
+fn emerald_collected(watchers: &Watchers, settings: &Settings) -> bool {
+    let Some(chaosemeralds) = &watchers.chaosemeralds.pair else {
+        return false;
+    };
+
+    watchers.currentgamemode == GameMode::SonicSimulator
+        && settings.sonic_simulator_emerald_split
+        && chaosemeralds.current > chaosemeralds.old
+}
+
+fn split(watchers: &Watchers, settings: &Settings) -> bool {
+    if watchers.levelid.pair.is_none() {
+        return false;
+    }
+    let Some(chaosemeralds) = &watchers.chaosemeralds.pair else {
+        return false;
+    };
+
+    if watchers.levelcleared.pair.is_none() {
+        return false;
+    }
+    let level_loaded = level_loaded(watchers);
+    let (left, stage) = split_target(watchers);
+
+    let cont = match watchers.currentgamemode {
+        GameMode::EggShuttle => egg_shuttle_split_enabled(watchers, settings, stage),
+        _ => settings.level_split(left) && settings.category.includes(left),
+    };
+
+    if !cont {
+        return rejected(watchers, settings, "split", "level disabled in settings");
+    }
+
+    // Bosses set to split on the final hit split there, and not again at the end of the stage
+    if left.is_boss() && settings.boss_final_hit(left) {
+        return match final_hit(watchers) {
+            true => true,
+            _ => rejected(watchers, settings, "split", "final hit on the boss"),
+        };
+    }
+
+    if let Some((_, act)) = left.sonic_simulator_act() {
+        if settings.sonic_simulator_world_splits && act != 3 {
+            return rejected(watchers, settings, "split", "not the end of a world");
+        }
+
+        if left == Levels::SonicSimulatorAct7_3
+            && settings.sonic_simulator_all_emeralds
+            && chaosemeralds.current < 7
+        {
+            return rejected(
+                watchers,
+                settings,
+                "split",
+                "not all Chaos Emeralds collected",
+            );
+        }
+    }
+
+    let Some(goalringreached) = watchers.stage_flag(StageFlags::GOAL_RING) else {
+        return false;
+    };
+    let goal_touched = goalringreached.current && !goalringreached.old;
+
+    // Rival Rush races have no goal ring to speak of: they split as soon as Metal Sonic is beaten
+    if left.is_rival_rush() {
+        let Some(rivalrush) = &watchers.rivalrush.pair else {
+            return false;
+        };
+        return rivalrush.current == RivalRushResult::Won && rivalrush.old != RivalRushResult::Won;
+    }
+
+    let timing = match watchers.currentgamemode {
+        GameMode::AnyPercent => settings.split_timing_anypercent,
+        GameMode::SonicSimulator => settings.split_timing_sonic_simulator,
+        GameMode::EggShuttle => settings.split_timing_egg_shuttle,
+    }
+    .resolve(settings.split_timing);
+
+    // The final stage has no results screen or stage transition after it, so it always splits on the goal ring
+    let (fired, waiting_for) = if final_stage(watchers, settings) {
+        (goal_touched, "final goal ring touch")
+    } else {
+        match timing {
+            SplitTiming::GoalRing => (goal_touched, "goal ring touch"),
+            SplitTiming::ResultsExit if watchers.currentgamemode == GameMode::EggShuttle => {
+                let Some(progressiveid) = &watchers.eggshuttle_progressiveid.pair else {
+                    return false;
+                };
+                (
+                    progressiveid.old.checked_add(1) == Some(progressiveid.current),
+                    "Egg Shuttle stage transition",
+                )
+            }
+            SplitTiming::ResultsExit => (
+                !goalringreached.current && goalringreached.old,
+                "results screen exit",
+            ),
+            SplitTiming::LevelLoad => (level_loaded, "next level load"),
+        }
+    };
+
+    if !fired {
+        return rejected(watchers, settings, "split", waiting_for);
+    }
+
+    true
+}
+
+/// Whether a stage is being loaded after the goal ring was reached in another one
+fn level_loaded(watchers: &Watchers) -> bool {
+    let (Some(levelid), Some(levelcleared)) = (&watchers.levelid.pair, &watchers.levelcleared.pair)
+    else {
+        return false;
+    };
+    levelid.changed() && levelid.current != Levels::None && levelcleared.old
+}
+
+/// The level a split would be for, along with its position in the Egg Shuttle order. That's the
+/// level being left, except when the next one is being loaded: the world map or a menu may have
+/// been shown in between, and the Egg Shuttle progress has already moved on by then.
+fn split_target(watchers: &Watchers) -> (Levels, u8) {
+    match level_loaded(watchers) {
+        true => (watchers.clearedlevel, watchers.clearedstage),
+        _ => (
+            watchers.levelid.pair.map_or(Levels::None, |x| x.old),
+            watchers.eggshuttle_progressiveid.pair.map_or(0, |x| x.old),
+        ),
+    }
+}
+
+/// Whether the level being left (in Egg Shuttle, the stage being cleared) is the last one of the run
+fn final_stage(watchers: &Watchers, settings: &Settings) -> bool {
+    let (left, stage) = split_target(watchers);
+
+    if watchers.currentgamemode == GameMode::EggShuttle {
+        let Some(totalstages) = &watchers.eggshuttle_totalstages.pair else {
+            return false;
+        };
+        egg_shuttle_final_stage(stage, totalstages.current)
+    } else {
+        left == Levels::TerminalVelocityAct2
+            || (watchers.currentgamemode == GameMode::SonicSimulator
+                && left == Levels::SonicSimulatorAct7_3)
+            || settings.category.final_level() == Some(left)
+    }
+}
+
+/// Egg Shuttle splits are filtered by the position of the stage just cleared in the
+/// Egg Shuttle order, rather than by whichever level happens to be loaded
+fn egg_shuttle_split_enabled(watchers: &Watchers, settings: &Settings, stage: u8) -> bool {
+    let Some(totalstages) = &watchers.eggshuttle_totalstages.pair else {
+        return false;
+    };
+
+    let stage = stage as usize;
+    let Some(&level) = EGG_SHUTTLE_STAGES.get(stage) else {
+        return false;
+    };
+
+    // The final stage ends the run, so it's never filtered out
+    if stage + 1 == totalstages.current as usize {
+        return true;
+    }
+
+    match settings.egg_shuttle_splits {
+        EggShuttleSplits::EveryStage => settings.level_split(level),
+        EggShuttleSplits::ZoneBoundaries => {
+            EGG_SHUTTLE_STAGES
+                .get(stage + 1)
+                .and_then(|next| next.zone())
+                != level.zone()
+        }
+        EggShuttleSplits::BossesOnly => level.is_boss(),
+        EggShuttleSplits::CustomStages => watchers.eggshuttlestages & (1 << stage) != 0,
+    }
+}
+
+/// Settings map key holding the Egg Shuttle stages to split on, for the custom split filter
+const EGG_SHUTTLE_STAGES_KEY: &str = "egg_shuttle_split_stages";
+
+/// Parses a list of Egg Shuttle stage numbers, counted from 1, such as `7, 14, 21-25`,
+/// into one bit per stage. Anything that isn't a stage number or range is skipped.
+fn parse_stage_list(list: &str) -> u64 {
+    let mut stages = 0u64;
+
+    for entry in list.split(',').map(str::trim) {
+        let (first, last) = entry.split_once('-').unwrap_or((entry, entry));
+        let (Ok(first), Ok(last)) = (first.trim().parse::<u8>(), last.trim().parse::<u8>()) else {
+            continue;
+        };
+
+        for stage in first.max(1)..=last.min(EGG_SHUTTLE_STAGES.len() as u8) {
+            stages |= 1 << (stage - 1);
+        }
+    }
+
+    stages
+}
+
+/// Whether `progressiveid` (counted from 0) is the last of `totalstages` Egg Shuttle stages
+fn egg_shuttle_final_stage(progressiveid: u8, totalstages: u8) -> bool {
+    progressiveid.checked_add(1) == Some(totalstages)
+}
+
+/// Checks a new reading of the Egg Shuttle counters, as `(totalstages, progressiveid)`,
+/// against the previous one. The stage count never changes during a run and progress
+/// can only stay put, advance by one stage, or start over from the first stage.
+fn egg_shuttle_counters_plausible(old: (u8, u8), current: (u8, u8)) -> bool {
+    let (old_totalstages, old_progressiveid) = old;
+    let (totalstages, progressiveid) = current;
+
+    (1..=45).contains(&totalstages)
+        && progressiveid < totalstages
+        && (old_totalstages == 0 || totalstages == old_totalstages)
+        && (progressiveid == old_progressiveid
+            || progressiveid == old_progressiveid.saturating_add(1)
+            || progressiveid == 0)
+}
+
+/// Applies the reset guards on top of the reset conditions checked in `reset`
+fn reset_guarded(watchers: &mut Watchers, settings: &Settings) -> bool {
+    let triggered = reset(watchers, settings);
+    let pending = core::mem::take(&mut watchers.resetpending);
+
+    if !triggered && !pending {
+        return rejected(watchers, settings, "reset", "reset conditions not met");
+    }
+
+    if settings.reset_never_after_end && watchers.runended {
+        return rejected(watchers, settings, "reset", "the run has ended");
+    }
+
+    if let Some(limit) = settings.reset_split_limit.split_index() {
+        if timer::current_split_index().is_some_and(|index| index >= limit) {
+            return rejected(watchers, settings, "reset", "past the split limit");
+        }
+    }
+
+    if !settings.reset_confirm {
+        triggered
+    } else if pending {
+        // The state that triggered the reset on the previous update must still be in place
+        match reset_holds(watchers, settings) {
+            true => true,
+            _ => rejected(
+                watchers,
+                settings,
+                "reset",
+                "not confirmed on the next update",
+            ),
+        }
+    } else {
+        watchers.resetpending = triggered;
+        rejected(
+            watchers,
+            settings,
+            "reset",
+            "waiting for confirmation on the next update",
+        )
+    }
+}
+
+fn reset(watchers: &Watchers, settings: &Settings) -> bool {
+    if watchers.currentgamemode == GameMode::EggShuttle {
+        let Some(state) = &watchers.eggshuttle_state.pair else {
+            return false;
+        };
+        let Some(igt) = &watchers.igt.pair else {
+            return false;
+        };
+        let Some(goalringreached) = watchers.stage_flag(StageFlags::GOAL_RING) else {
+            return false;
+        };
+
+        // Deaths the run can continue from leave the state untouched, so only
+        // an actual game over or a quit to the menu can trigger a reset
+        settings.reset_egg_shuttle
+            && match state.current {
+                EggShuttleState::GameOver => {
+                    state.changed() && settings.reset_egg_shuttle_game_over
+                }
+                EggShuttleState::Quit => state.changed() && settings.reset_egg_shuttle_quit,
+                EggShuttleState::Playing => false,
+                // The session object may be gone after quitting: fall back to the IGT
+                // resetting without the goal ring having been reached
+                EggShuttleState::Unknown => {
+                    (settings.reset_egg_shuttle_game_over || settings.reset_egg_shuttle_quit)
+                        && igt.old != Duration::ZERO
+                        && igt.current == Duration::ZERO
+                        && !goalringreached.old
+                }
+            }
+    } else {
+        let Some(runstart) = &watchers.runstart.pair else {
+            return false;
+        };
+        let Some(rivalrush) = &watchers.rivalrush.pair else {
+            return false;
+        };
+
+        if settings.category == Category::RivalRush {
+            settings.reset_rival_rush
+                && rivalrush.current == RivalRushResult::Lost
+                && rivalrush.old != RivalRushResult::Lost
+        } else {
+            settings.reset_anypercent && runstart.old == 110 && runstart.current == 35
+        }
+    }
+}
+
+/// Whether the state `reset` reacts to is still in place, for confirming a reset on the
+/// update after it triggered
+fn reset_holds(watchers: &Watchers, settings: &Settings) -> bool {
+    if watchers.currentgamemode == GameMode::EggShuttle {
+        let Some(state) = &watchers.eggshuttle_state.pair else {
+            return false;
+        };
+        let Some(igt) = &watchers.igt.pair else {
+            return false;
+        };
+        let Some(goalringreached) = watchers.stage_flag(StageFlags::GOAL_RING) else {
+            return false;
+        };
+
+        match state.current {
+            EggShuttleState::GameOver => settings.reset_egg_shuttle_game_over,
+            EggShuttleState::Quit => settings.reset_egg_shuttle_quit,
+            EggShuttleState::Playing => false,
+            EggShuttleState::Unknown => igt.current == Duration::ZERO && !goalringreached.current,
+        }
+    } else {
+        let Some(runstart) = &watchers.runstart.pair else {
+            return false;
+        };
+        let Some(rivalrush) = &watchers.rivalrush.pair else {
+            return false;
+        };
+
+        match settings.category {
+            Category::RivalRush => rivalrush.current == RivalRushResult::Lost,
+            _ => runstart.current == 35,
+        }
+    }
+}
+
+fn is_loading(watchers: &Watchers, settings: &Settings) -> Option<bool> {
+    match settings.timing_method {
+        // Without the time of the previous acts, LiveSplit's own game time is kept running
+        // for as long as the act's IGT is
+        TimingMethod::InGameTime if watchers.gametimeunknown => {
+            Some(watchers.igtstillticks >= IGT_STILL_TICKS)
+        }
+        TimingMethod::InGameTime => Some(true),
+        TimingMethod::RealTimeWithoutPauses => Some(watchers.paused.pair?.current),
+    }
+}
+
+fn game_time(watchers: &Watchers, settings: &Settings) -> Option<Duration> {
+    match settings.timing_method {
+        // Without the time of the previous acts, LiveSplit's own game time is the better total
+        TimingMethod::InGameTime if watchers.gametimeunknown => None,
+        // Game time never goes backwards, whatever the game reports
+        TimingMethod::InGameTime => {
+            let igt = watchers.igt.pair?.current;
+            let igt = watchers.bosslatch.map_or(igt, |latch| igt.min(latch));
+            Some((igt + watchers.accumulatedigt + watchers.startoffset).max(watchers.lastgametime))
+        }
+        TimingMethod::RealTimeWithoutPauses => None,
+    }
+}
+
+fn start_offset(watchers: &Watchers, settings: &Settings) -> Duration {
+    match watchers.currentgamemode {
+        GameMode::EggShuttle => settings.start_offset_egg_shuttle,
+        _ if settings.category != Category::FullGame => settings.start_offset_category,
+        GameMode::SonicSimulator => settings.start_offset_sonic_simulator,
+        GameMode::AnyPercent => settings.start_offset_anypercent,
+    }
+    .duration()
+}
+
+/// Number of updates without the IGT going up after which it is considered stopped. The IGT
+/// only changes once per frame, which is less often than the auto splitter updates.
+const IGT_STILL_TICKS: u32 = 10;
+
+/// Settings map key holding the runner's trigger boxes, as a list of
+/// `stage code, min x, min y, min z, max x, max y, max z, label` entries separated by `;`
+const CUSTOM_SPLITS_KEY: &str = "custom_split_triggers";
+/// Maximum number of trigger boxes, one bit each in `CustomSplits::fired`
+const CUSTOM_SPLITS_CAPACITY: usize = 32;
+/// Number of updates between two looks at the definitions kept in the settings map
+const DEFINITIONS_RELOAD_TICKS: u32 = 120;
+
+/// An axis-aligned box inside an act that splits when Sonic enters it
+struct TriggerBox {
+    level: Levels,
+    min: [f32; 3],
+    max: [f32; 3],
+    label: ArrayString<32>,
+}
+
+impl TriggerBox {
+    fn parse(entry: &str) -> Option<Self> {
+        let mut fields = entry.splitn(8, ',').map(str::trim);
+
+        let level = Levels::from_code(fields.next()?.as_bytes());
+        if level == Levels::None {
+            return None;
+        }
+
+        let mut coordinates = [0.0f32; 6];
+        for coordinate in &mut coordinates {
+            *coordinate = fields.next()?.parse().ok()?;
+        }
+        let [x1, y1, z1, x2, y2, z2] = coordinates;
+
+        let mut label = ArrayString::new();
+        let _ = label.try_push_str(fields.next().unwrap_or_default());
+
+        Some(Self {
+            level,
+            min: [x1.min(x2), y1.min(y2), z1.min(z2)],
+            max: [x1.max(x2), y1.max(y2), z1.max(z2)],
+            label,
+        })
+    }
+
+    fn contains(&self, position: [f32; 3]) -> bool {
+        (0..3).all(|axis| self.min[axis] <= position[axis] && position[axis] <= self.max[axis])
+    }
+}
+
+#[derive(Default)]
+struct CustomSplits {
+    /// The definitions the triggers were parsed from
+    source: ArrayString<2048>,
+    triggers: ArrayVec<TriggerBox, CUSTOM_SPLITS_CAPACITY>,
+    /// One bit per trigger that already fired in the current act attempt
+    fired: u32,
+}
+
+impl CustomSplits {
+    /// Picks up changes to the trigger definitions. Malformed entries are skipped.
+    fn reload(&mut self) {
+        let map = settings::Map::load();
+        let load = |key| {
+            map.get(key)
+                .and_then(|value| value.get_array_string::<2048>())
+                .and_then(Result::ok)
+                .unwrap_or_default()
+        };
+
+        let source = load(CUSTOM_SPLITS_KEY);
+        if source.as_str() != self.source.as_str() {
+            self.triggers.clear();
+            for trigger in source.split(';').filter_map(TriggerBox::parse) {
+                if self.triggers.try_push(trigger).is_err() {
+                    break;
+                }
+            }
+            self.source = source;
+            self.fired = 0;
+        }
+    }
+}
+
+const RUN_LOG_CAPACITY: usize = 128;
+const RUN_SUMMARY_CAPACITY: usize = 16384;
+
+/// Displays a duration the way LiveSplit does, e.g. `1:23.45`
+struct FormattedTime(Duration);
+
+impl fmt::Display for FormattedTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let centiseconds = self.0.whole_milliseconds().max(0) / 10;
+        let (minutes, centiseconds) = (centiseconds / 6000, centiseconds % 6000);
+        write!(
+            f,
+            "{}:{:02}.{:02}",
+            minutes,
+            centiseconds / 100,
+            centiseconds % 100
+        )
+    }
+}
+
+#[derive(Clone, Copy)]
+struct ActRecord {
+    level: Levels,
+    restarts: u16,
+    /// Act IGT at the moment the act was completed
+    igt: Option<Duration>,
+}
+
+impl ActRecord {
+    const fn new(level: Levels) -> Self {
+        Self {
+            level,
+            restarts: 0,
+            igt: None,
+        }
+    }
+}
+
+/// Acts played during the current run, in the order they were entered
+struct RunLog {
+    acts: [ActRecord; RUN_LOG_CAPACITY],
+    len: usize,
+    restarts: u32,
+}
+
+impl Default for RunLog {
+    fn default() -> Self {
+        Self {
+            acts: [ActRecord::new(Levels::None); RUN_LOG_CAPACITY],
+            len: 0,
+            restarts: 0,
+        }
+    }
+}
+
+impl RunLog {
+    fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    fn acts(&self) -> &[ActRecord] {
+        &self.acts[..self.len]
+    }
+
+    /// Starts a new record, unless the level is the one already being recorded
+    fn enter(&mut self, level: Levels) {
+        if self.acts().last().is_some_and(|act| act.level == level) || self.len == RUN_LOG_CAPACITY
+        {
+            return;
+        }
+
+        self.acts[self.len] = ActRecord::new(level);
+        self.len += 1;
+    }
+
+    fn current_act(&self, level: Levels) -> Option<&ActRecord> {
+        self.acts().last().filter(|act| act.level == level)
+    }
+
+    fn current_act_mut(&mut self, level: Levels) -> Option<&mut ActRecord> {
+        self.acts[..self.len]
+            .last_mut()
+            .filter(|act| act.level == level)
+    }
+
+    fn record_completion(&mut self, level: Levels, igt: Duration) {
+        if let Some(act) = self.current_act_mut(level) {
+            act.igt = Some(igt);
+        }
+    }
+
+    fn record_restart(&mut self, level: Levels) {
+        self.restarts += 1;
+        if let Some(act) = self.current_act_mut(level) {
+            act.restarts = act.restarts.saturating_add(1);
+        }
+    }
+
+    fn print(&self) {
+        asr::print_limited::<128>(&format_args!("Run summary: {} restarts", self.restarts));
+        asr::print_message("Level | IGT | Restarts | Total");
+
+        let mut total = Duration::ZERO;
+        for act in self.acts() {
+            match act.igt {
+                Some(igt) => {
+                    total += igt;
+                    asr::print_limited::<128>(&format_args!(
+                        "{} | {} | {} | {}",
+                        act.level.name(),
+                        FormattedTime(igt),
+                        act.restarts,
+                        FormattedTime(total)
+                    ));
+                }
+                _ => asr::print_limited::<128>(&format_args!(
+                    "{} | - | {} | {}",
+                    act.level.name(),
+                    act.restarts,
+                    FormattedTime(total)
+                )),
+            }
+        }
+    }
+
+    /// Serializes the completed acts as a JSON array, for use in a timer variable
+    fn to_json(&self) -> ArrayString<RUN_SUMMARY_CAPACITY> {
+        let mut buf = ArrayString::new();
+        let mut total = Duration::ZERO;
+        let _ = buf.write_char('[');
+
+        for (i, act) in self
+            .acts()
+            .iter()
+            .filter(|act| act.igt.is_some())
+            .enumerate()
+        {
+            let igt = act.igt.unwrap_or_default();
+            total += igt;
+            let _ = write!(
+                buf,
+                r#"{}{{"level":"{}","igt":"{}","restarts":{},"total":"{}"}}"#,
+                if i == 0 { "" } else { "," },
+                act.level.name(),
+                FormattedTime(igt),
+                act.restarts,
+                FormattedTime(total)
+            );
+        }
+
+        let _ = buf.write_char(']');
+        buf
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum HookState {
+    Scanning,
+    Hooked,
+    Degraded,
+    Failed,
+}
+
+impl HookState {
+    fn publish(self) {
+        timer::set_variable(
+            "Hook state",
+            match self {
+                Self::Scanning => "Scanning",
+                Self::Hooked => "Hooked",
+                Self::Degraded => "Degraded (autosplitting unavailable)",
+                Self::Failed => "Failed (unsupported game version)",
+            },
+        );
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Default)]
+enum GameMode {
+    #[default]
+    AnyPercent,
+    SonicSimulator,
+    EggShuttle,
+}
+
+/// Status bitfield of the current stage, read from `addr_3 + 0x110`.
+/// Only the bits the splitter relies on are named; the others are shown by position in the
+/// "Stage flags" variable and the debug log, so they can be mapped out before being used.
+#[derive(Clone, Copy, PartialEq, Default)]
+struct StageFlags(u8);
+
+impl StageFlags {
+    /// Set when the goal ring is touched, cleared when leaving the results screen
+    const GOAL_RING: Self = Self(1 << 5);
+
+    const NAMES: [(Self, &'static str); 1] = [(Self::GOAL_RING, "goal ring")];
+
+    fn contains(self, flag: Self) -> bool {
+        self.0 & flag.0 == flag.0
+    }
+}
+
+impl fmt::Display for StageFlags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0 == 0 {
+            return f.write_str("none");
+        }
+
+        let mut separator = "";
+        for bit in 0..8 {
+            let flag = Self(1 << bit);
+            if !self.contains(flag) {
+                continue;
+            }
+
+            f.write_str(separator)?;
+            separator = ", ";
+            match Self::NAMES.iter().find(|(named, _)| *named == flag) {
+                Some((_, name)) => f.write_str(name)?,
+                _ => write!(f, "bit {bit}")?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The part of the run state that can't be read back from the game, mirrored into the
+/// settings map so it survives the auto splitter being reloaded mid-run. As the settings
+/// map is kept in the layout, it is only written when this state changes.
+#[derive(Clone, Copy, PartialEq)]
+struct SavedRun {
+    game_mode: GameMode,
+    accumulated_igt: Duration,
+    start_offset: Duration,
+    /// The game time last sent to the timer, as of the last time anything else changed
+    game_time: Duration,
+    split_index: u64,
+}
+
+impl SavedRun {
+    const ACTIVE: &'static str = "saved_run_active";
+    const GAME_MODE: &'static str = "saved_run_game_mode";
+    const ACCUMULATED_IGT: &'static str = "saved_run_accumulated_igt";
+    const START_OFFSET: &'static str = "saved_run_start_offset";
+    const GAME_TIME: &'static str = "saved_run_game_time";
+    const SPLIT_INDEX: &'static str = "saved_run_split_index";
+
+    fn store(watchers: &mut Watchers) {
+        if watchers.gametimeunknown {
+            return;
+        }
+
+        let saved = Self {
+            game_mode: watchers.currentgamemode,
+            accumulated_igt: watchers.accumulatedigt,
+            start_offset: watchers.startoffset,
+            game_time: watchers.lastgametime,
+            split_index: timer::current_split_index().unwrap_or_default(),
+        };
+        // The game time alone changes on every update: it is only a lower bound on resume
+        if watchers.savedrun.is_some_and(|last| {
+            Self {
+                game_time: last.game_time,
+                ..saved
+            } == last
+        }) {
+            return;
+        }
+        watchers.savedrun = Some(saved);
+
+        Self::update(|map| {
+            map.insert(Self::ACTIVE, &settings::Value::from(true));
+            map.insert(
+                Self::GAME_MODE,
+                &settings::Value::from(saved.game_mode as i64),
+            );
+            map.insert(
+                Self::ACCUMULATED_IGT,
+                &settings::Value::from(saved.accumulated_igt.as_seconds_f64()),
+            );
+            map.insert(
+                Self::START_OFFSET,
+                &settings::Value::from(saved.start_offset.as_seconds_f64()),
+            );
+            map.insert(
+                Self::GAME_TIME,
+                &settings::Value::from(saved.game_time.as_seconds_f64()),
+            );
+            map.insert(
+                Self::SPLIT_INDEX,
+                &settings::Value::from(saved.split_index as i64),
+            );
+        });
+    }
+
+    /// Marks the saved state as belonging to a run that is over. The settings map is left
+    /// untouched when it holds no state for a run.
+    fn clear(watchers: &mut Watchers) {
+        watchers.savedrun = None;
+        if Self::load().is_some() {
+            Self::update(|map| map.insert(Self::ACTIVE, &settings::Value::from(false)));
+        }
+    }
+
+    /// Changes the settings map without clobbering a settings change made at the same time
+    fn update(f: impl Fn(&settings::Map)) {
+        loop {
+            let old = settings::Map::load();
+            let new = old.clone();
+            f(&new);
+            if new.store_if_unchanged(&old) {
+                break;
+            }
+        }
+    }
+
+    fn load() -> Option<Self> {
+        let map = settings::Map::load();
+        if !map.get(Self::ACTIVE)?.get_bool()? {
+            return None;
+        }
+
+        let seconds = |key| {
+            map.get(key)?
+                .get_f64()
+                .filter(|secs| secs.is_finite() && *secs >= 0.0)
+                .map(Duration::seconds_f64)
+        };
+
+        Some(Self {
+            game_mode: match map.get(Self::GAME_MODE)?.get_i64()? {
+                0 => GameMode::AnyPercent,
+                1 => GameMode::SonicSimulator,
+                2 => GameMode::EggShuttle,
+                _ => return None,
+            },
+            accumulated_igt: seconds(Self::ACCUMULATED_IGT)?,
+            start_offset: seconds(Self::START_OFFSET)?,
+            game_time: seconds(Self::GAME_TIME)?,
+            split_index: map.get(Self::SPLIT_INDEX)?.get_i64()? as u64,
+        })
+    }
+}
+
+/// Color Powers. The IDs the player object uses for them are assumed to follow this order,
+/// which hasn't been confirmed yet.
+#[derive(Clone, Copy, PartialEq, Default)]
+enum Wisp {
+    #[default]
+    None,
+    Laser,
+    Drill,
+    Rocket,
+    Spikes,
+    Hover,
+    Cube,
+    Frenzy,
+    Burst,
+    Ghost,
+}
+
+impl Wisp {
+    const COUNT: usize = 9;
+    const ALL: [Self; Self::COUNT] = [
+        Self::Laser,
+        Self::Drill,
+        Self::Rocket,
+        Self::Spikes,
+        Self::Hover,
+        Self::Cube,
+        Self::Frenzy,
+        Self::Burst,
+        Self::Ghost,
+    ];
+
+    fn from_id(id: u8) -> Self {
+        match id {
+            1..=9 => Self::ALL[id as usize - 1],
+            _ => Self::None,
+        }
+    }
+
+    const fn name(self) -> &'static str {
+        match self {
+            Self::None => "None",
+            Self::Laser => "Laser",
+            Self::Drill => "Drill",
+            Self::Rocket => "Rocket",
+            Self::Spikes => "Spikes",
+            Self::Hover => "Hover",
+            Self::Cube => "Cube",
+            Self::Frenzy => "Frenzy",
+            Self::Burst => "Burst",
+            Self::Ghost => "Ghost",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Default)]
+enum EggShuttleState {
+    Playing,
+    GameOver,
+    Quit,
+    /// The state could not be read, e.g. because there's no Egg Shuttle session
+    #[default]
+    Unknown,
+}
+
+#[derive(Clone, Copy, PartialEq, Default)]
+enum RivalRushResult {
+    #[default]
+    None,
+    Won,
+    Lost,
+}
+
+#[derive(Gui, Clone, Copy, PartialEq)]
+enum EggShuttleSplits {
+    /// Every stage (as selected in the level settings)
+    #[default]
+    EveryStage,
+    /// Zone boundaries
+    ZoneBoundaries,
+    /// Bosses only
+    BossesOnly,
+    /// Custom list of stage numbers (egg_shuttle_split_stages)
+    CustomStages,
+}
+
+#[derive(Gui, Clone, Copy, PartialEq)]
+enum ParkTokenSplit {
+    /// Disabled
+    #[default]
+    Off,
+    /// 100 Park Tokens
+    Tokens100,
+    /// 250 Park Tokens
+    Tokens250,
+    /// 500 Park Tokens
+    Tokens500,
+    /// 1000 Park Tokens
+    Tokens1000,
+    /// 2500 Park Tokens
+    Tokens2500,
+    /// 5000 Park Tokens
+    Tokens5000,
+}
+
+impl ParkTokenSplit {
+    const fn threshold(self) -> Option<u32> {
+        match self {
+            Self::Off => None,
+            Self::Tokens100 => Some(100),
+            Self::Tokens250 => Some(250),
+            Self::Tokens500 => Some(500),
+            Self::Tokens1000 => Some(1000),
+            Self::Tokens2500 => Some(2500),
+            Self::Tokens5000 => Some(5000),
+        }
+    }
+}
+
+#[derive(Gui, Clone, Copy, PartialEq)]
+enum AnyPercentStart {
+    /// New save file created
+    NewSaveCreated,
+    /// Save slot confirmed
+    #[default]
+    SaveSlotConfirmed,
+    /// First control in Tropical Resort - Act 1
+    FirstControl,
+}
+
+#[derive(Gui, Clone, Copy, PartialEq)]
+enum TimingMethod {
+    /// In-game time
+    #[default]
+    InGameTime,
+    /// Real time, paused while the pause menu is open
+    RealTimeWithoutPauses,
+}
+
+#[derive(Gui, Clone, Copy, PartialEq)]
+enum TelemetryRate {
+    /// Disabled
+    #[default]
+    Off,
+    /// Every update
+    EveryUpdate,
+    /// 30 times per second
+    Hz30,
+    /// 10 times per second
+    Hz10,
+    /// Twice per second
+    Hz2,
+}
+
+impl TelemetryRate {
+    /// Number of updates between two refreshes, at the default tick rate of 120 updates per second
+    const fn interval_ticks(self) -> Option<u32> {
+        match self {
+            Self::Off => None,
+            Self::EveryUpdate => Some(1),
+            Self::Hz30 => Some(4),
+            Self::Hz10 => Some(12),
+            Self::Hz2 => Some(60),
+        }
+    }
+}
+
+#[derive(Gui, Clone, Copy, PartialEq)]
+enum StartOffset {
+    /// None
+    #[default]
+    None,
+    /// 0.10 seconds
+    Ms100,
+    /// 0.25 seconds
+    Ms250,
+    /// 0.50 seconds
+    Ms500,
+    /// 1.00 seconds
+    Ms1000,
+    /// 2.00 seconds
+    Ms2000,
+    /// 3.00 seconds
+    Ms3000,
+}
+
+impl StartOffset {
+    const fn duration(self) -> Duration {
+        match self {
+            Self::None => Duration::ZERO,
+            Self::Ms100 => Duration::milliseconds(100),
+            Self::Ms250 => Duration::milliseconds(250),
+            Self::Ms500 => Duration::milliseconds(500),
+            Self::Ms1000 => Duration::milliseconds(1000),
+            Self::Ms2000 => Duration::milliseconds(2000),
+            Self::Ms3000 => Duration::milliseconds(3000),
+        }
+    }
+}
+
+#[derive(Gui, Clone, Copy, PartialEq)]
+enum ResetSplitLimit {
+    /// Disabled
+    #[default]
+    Off,
+    /// Split 1
+    Split1,
+    /// Split 5
+    Split5,
+    /// Split 10
+    Split10,
+    /// Split 20
+    Split20,
+    /// Split 30
+    Split30,
+    /// Split 40
+    Split40,
+}
+
+impl ResetSplitLimit {
+    const fn split_index(self) -> Option<u64> {
+        match self {
+            Self::Off => None,
+            Self::Split1 => Some(1),
+            Self::Split5 => Some(5),
+            Self::Split10 => Some(10),
+            Self::Split20 => Some(20),
+            Self::Split30 => Some(30),
+            Self::Split40 => Some(40),
+        }
+    }
+}
+
+#[derive(Gui, Clone, Copy, PartialEq)]
+enum SplitTiming {
+    /// Goal ring touch
+    GoalRing,
+    /// Results screen exit (Egg Shuttle: stage transition)
+    #[default]
+    ResultsExit,
+    /// Next level load
+    LevelLoad,
+}
+
+#[derive(Gui, Clone, Copy, PartialEq)]
+enum SplitTimingOverride {
+    /// Use global setting
+    #[default]
+    Global,
+    /// Goal ring touch
+    GoalRing,
+    /// Results screen exit (Egg Shuttle: stage transition)
+    ResultsExit,
+    /// Next level load
+    LevelLoad,
+}
+
+#[derive(Gui, Clone, Copy, PartialEq)]
+enum Category {
+    /// Full game (Any% / Sonic Simulator)
+    #[default]
+    FullGame,
+    /// All Bosses
+    AllBosses,
+    /// Tropical Resort only
+    TropicalResort,
+    /// Sweet Mountain only
+    SweetMountain,
+    /// Starlight Carnival only
+    StarlightCarnival,
+    /// Planet Wisp only
+    PlanetWisp,
+    /// Aquarium Park only
+    AquariumPark,
+    /// Asteroid Coaster only
+    AsteroidCoaster,
+    /// Terminal Velocity only
+    TerminalVelocity,
+    /// Rival Rush
+    RivalRush,
+}
+
+impl Category {
+    const fn zone(self) -> Option<Zone> {
+        match self {
+            Self::TropicalResort => Some(Zone::TropicalResort),
+            Self::SweetMountain => Some(Zone::SweetMountain),
+            Self::StarlightCarnival => Some(Zone::StarlightCarnival),
+            Self::PlanetWisp => Some(Zone::PlanetWisp),
+            Self::AquariumPark => Some(Zone::AquariumPark),
+            Self::AsteroidCoaster => Some(Zone::AsteroidCoaster),
+            Self::TerminalVelocity => Some(Zone::TerminalVelocity),
+            _ => None,
+        }
+    }
+
+    /// The level whose entry starts the timer. `None` for the full game, which uses its own start conditions.
+    const fn first_level(self) -> Option<Levels> {
+        match self {
+            Self::FullGame => None,
+            Self::AllBosses => Some(Levels::TropicalResortBoss),
+            Self::RivalRush => Some(Levels::RivalRushTropicalResort),
+            _ => match self.zone() {
+                Some(zone) => Some(zone.first_level()),
+                _ => None,
+            },
+        }
+    }
+
+    /// The level whose goal ring ends the run
+    const fn final_level(self) -> Option<Levels> {
+        match self {
+            Self::FullGame => None,
+            Self::AllBosses => Some(Levels::TerminalVelocityBoss),
+            Self::RivalRush => Some(Levels::RivalRushAsteroidCoaster),
+            _ => match self.zone() {
+                Some(zone) => Some(zone.final_level()),
+                _ => None,
+            },
+        }
+    }
+
+    fn includes(self, level: Levels) -> bool {
+        match self {
+            Self::FullGame => true,
+            Self::AllBosses => level.is_boss(),
+            Self::RivalRush => level.is_rival_rush(),
+            _ => self.zone().is_some() && level.zone() == self.zone(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Zone {
+    TropicalResort,
+    SweetMountain,
+    StarlightCarnival,
+    PlanetWisp,
+    AquariumPark,
+    AsteroidCoaster,
+    TerminalVelocity,
+}
+
+impl Zone {
+    const fn first_level(self) -> Levels {
+        match self {
+            Self::TropicalResort => Levels::TropicalResortAct1,
+            Self::SweetMountain => Levels::SweetMountainAct1,
+            Self::StarlightCarnival => Levels::StarlightCarnivalAct1,
+            Self::PlanetWisp => Levels::PlanetWispAct1,
+            Self::AquariumPark => Levels::AquariumParkAct1,
+            Self::AsteroidCoaster => Levels::AsteroidCoasterAct1,
+            Self::TerminalVelocity => Levels::TerminalVelocityAct1,
+        }
+    }
+
+    const fn final_level(self) -> Levels {
+        match self {
+            Self::TropicalResort => Levels::TropicalResortBoss,
+            Self::SweetMountain => Levels::SweetMountainBoss,
+            Self::StarlightCarnival => Levels::StarlightCarnivalBoss,
+            Self::PlanetWisp => Levels::PlanetWispBoss,
+            Self::AquariumPark => Levels::AquariumParkBoss,
+            Self::AsteroidCoaster => Levels::AsteroidCoasterBoss,
+            Self::TerminalVelocity => Levels::TerminalVelocityAct2,
+        }
+    }
+}
+
+impl SplitTimingOverride {
+    const fn resolve(self, global: SplitTiming) -> SplitTiming {
+        match self {
+            Self::Global => global,
+            Self::GoalRing => SplitTiming::GoalRing,
+            Self::ResultsExit => SplitTiming::ResultsExit,
+            Self::LevelLoad => SplitTiming::LevelLoad,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Default)]
+enum Levels {
+    TropicalResortAct1,
+    TropicalResortAct2,
+    TropicalResortAct3,
+    TropicalResortAct4,
+    TropicalResortAct5,
+    TropicalResortAct6,
+    TropicalResortBoss,
+    SweetMountainAct1,
+    SweetMountainAct2,
+    SweetMountainAct3,
+    SweetMountainAct4,
+    SweetMountainAct5,
+    SweetMountainAct6,
+    SweetMountainBoss,
+    StarlightCarnivalAct1,
+    StarlightCarnivalAct2,
+    StarlightCarnivalAct3,
     StarlightCarnivalAct4,
     StarlightCarnivalAct5,
     StarlightCarnivalAct6,
@@ -749,5 +2946,501 @@ enum Levels {
     SonicSimulatorAct7_1,
     SonicSimulatorAct7_2,
     SonicSimulatorAct7_3,
+    RivalRushTropicalResort,
+    RivalRushSweetMountain,
+    RivalRushStarlightCarnival,
+    RivalRushPlanetWisp,
+    RivalRushAquariumPark,
+    RivalRushAsteroidCoaster,
+    #[default]
     None,
 }
+
+/// Order in which the stages are played in Egg Shuttle
+const EGG_SHUTTLE_STAGES: [Levels; 45] = [
+    Levels::TropicalResortAct1,
+    Levels::TropicalResortAct2,
+    Levels::TropicalResortAct3,
+    Levels::TropicalResortAct4,
+    Levels::TropicalResortAct5,
+    Levels::TropicalResortAct6,
+    Levels::TropicalResortBoss,
+    Levels::SweetMountainAct1,
+    Levels::SweetMountainAct2,
+    Levels::SweetMountainAct3,
+    Levels::SweetMountainAct4,
+    Levels::SweetMountainAct5,
+    Levels::SweetMountainAct6,
+    Levels::SweetMountainBoss,
+    Levels::StarlightCarnivalAct1,
+    Levels::StarlightCarnivalAct2,
+    Levels::StarlightCarnivalAct3,
+    Levels::StarlightCarnivalAct4,
+    Levels::StarlightCarnivalAct5,
+    Levels::StarlightCarnivalAct6,
+    Levels::StarlightCarnivalBoss,
+    Levels::PlanetWispAct1,
+    Levels::PlanetWispAct2,
+    Levels::PlanetWispAct3,
+    Levels::PlanetWispAct4,
+    Levels::PlanetWispAct5,
+    Levels::PlanetWispAct6,
+    Levels::PlanetWispBoss,
+    Levels::AquariumParkAct1,
+    Levels::AquariumParkAct2,
+    Levels::AquariumParkAct3,
+    Levels::AquariumParkAct4,
+    Levels::AquariumParkAct5,
+    Levels::AquariumParkAct6,
+    Levels::AquariumParkBoss,
+    Levels::AsteroidCoasterAct1,
+    Levels::AsteroidCoasterAct2,
+    Levels::AsteroidCoasterAct3,
+    Levels::AsteroidCoasterAct4,
+    Levels::AsteroidCoasterAct5,
+    Levels::AsteroidCoasterAct6,
+    Levels::AsteroidCoasterBoss,
+    Levels::TerminalVelocityAct1,
+    Levels::TerminalVelocityBoss,
+    Levels::TerminalVelocityAct2,
+];
+
+impl Levels {
+    /// Maps a stage code as found in memory (e.g. `stg110`) to the level it belongs to
+    fn from_code(code: &[u8]) -> Self {
+        match code {
+            b"stg110" => Self::TropicalResortAct1,
+            b"stg130" => Self::TropicalResortAct2,
+            b"stg120" => Self::TropicalResortAct3,
+            b"stg140" => Self::TropicalResortAct4,
+            b"stg150" => Self::TropicalResortAct5,
+            b"stg160" => Self::TropicalResortAct6,
+            b"stg190" => Self::TropicalResortBoss,
+            b"stg210" => Self::SweetMountainAct1,
+            b"stg230" => Self::SweetMountainAct2,
+            b"stg220" => Self::SweetMountainAct3,
+            b"stg260" => Self::SweetMountainAct4,
+            b"stg240" => Self::SweetMountainAct5,
+            b"stg250" => Self::SweetMountainAct6,
+            b"stg290" => Self::SweetMountainBoss,
+            b"stg310" => Self::StarlightCarnivalAct1,
+            b"stg330" => Self::StarlightCarnivalAct2,
+            b"stg340" => Self::StarlightCarnivalAct3,
+            b"stg350" => Self::StarlightCarnivalAct4,
+            b"stg320" => Self::StarlightCarnivalAct5,
+            b"stg360" => Self::StarlightCarnivalAct6,
+            b"stg390" => Self::StarlightCarnivalBoss,
+            b"stg410" => Self::PlanetWispAct1,
+            b"stg440" => Self::PlanetWispAct2,
+            b"stg450" => Self::PlanetWispAct3,
+            b"stg430" => Self::PlanetWispAct4,
+            b"stg460" => Self::PlanetWispAct5,
+            b"stg420" => Self::PlanetWispAct6,
+            b"stg490" => Self::PlanetWispBoss,
+            b"stg510" => Self::AquariumParkAct1,
+            b"stg540" => Self::AquariumParkAct2,
+            b"stg550" => Self::AquariumParkAct3,
+            b"stg530" => Self::AquariumParkAct4,
+            b"stg560" => Self::AquariumParkAct5,
+            b"stg520" => Self::AquariumParkAct6,
+            b"stg590" => Self::AquariumParkBoss,
+            b"stg610" => Self::AsteroidCoasterAct1,
+            b"stg630" => Self::AsteroidCoasterAct2,
+            b"stg640" => Self::AsteroidCoasterAct3,
+            b"stg650" => Self::AsteroidCoasterAct4,
+            b"stg660" => Self::AsteroidCoasterAct5,
+            b"stg620" => Self::AsteroidCoasterAct6,
+            b"stg690" => Self::AsteroidCoasterBoss,
+            b"stg710" => Self::TerminalVelocityAct1,
+            b"stg790" => Self::TerminalVelocityBoss,
+            b"stg720" => Self::TerminalVelocityAct2,
+            b"stgD10" => Self::SonicSimulatorAct1_1,
+            b"stgB20" => Self::SonicSimulatorAct1_2,
+            b"stgE50" => Self::SonicSimulatorAct1_3,
+            b"stgD20" => Self::SonicSimulatorAct2_1,
+            b"stgB30" => Self::SonicSimulatorAct2_2,
+            b"stgF30" => Self::SonicSimulatorAct2_3,
+            b"stgG10" => Self::SonicSimulatorAct3_1,
+            b"stgG30" => Self::SonicSimulatorAct3_2,
+            b"stgA10" => Self::SonicSimulatorAct3_3,
+            b"stgD30" => Self::SonicSimulatorAct4_1,
+            b"stgG20" => Self::SonicSimulatorAct4_2,
+            b"stgC50" => Self::SonicSimulatorAct4_3,
+            b"stgE30" => Self::SonicSimulatorAct5_1,
+            b"stgB10" => Self::SonicSimulatorAct5_2,
+            b"stgE40" => Self::SonicSimulatorAct5_3,
+            b"stgG40" => Self::SonicSimulatorAct6_1,
+            b"stgC40" => Self::SonicSimulatorAct6_2,
+            b"stgF40" => Self::SonicSimulatorAct6_3,
+            b"stgA30" => Self::SonicSimulatorAct7_1,
+            b"stgE20" => Self::SonicSimulatorAct7_2,
+            b"stgC10" => Self::SonicSimulatorAct7_3,
+            b"stg180" => Self::RivalRushTropicalResort,
+            b"stg280" => Self::RivalRushSweetMountain,
+            b"stg380" => Self::RivalRushStarlightCarnival,
+            b"stg480" => Self::RivalRushPlanetWisp,
+            b"stg580" => Self::RivalRushAquariumPark,
+            b"stg680" => Self::RivalRushAsteroidCoaster,
+            _ => Self::None,
+        }
+    }
+
+    const fn name(self) -> &'static str {
+        match self {
+            Self::TropicalResortAct1 => "Tropical Resort - Act 1",
+            Self::TropicalResortAct2 => "Tropical Resort - Act 2",
+            Self::TropicalResortAct3 => "Tropical Resort - Act 3",
+            Self::TropicalResortAct4 => "Tropical Resort - Act 4",
+            Self::TropicalResortAct5 => "Tropical Resort - Act 5",
+            Self::TropicalResortAct6 => "Tropical Resort - Act 6",
+            Self::TropicalResortBoss => "Tropical Resort - Boss",
+            Self::SweetMountainAct1 => "Sweet Mountain - Act 1",
+            Self::SweetMountainAct2 => "Sweet Mountain - Act 2",
+            Self::SweetMountainAct3 => "Sweet Mountain - Act 3",
+            Self::SweetMountainAct4 => "Sweet Mountain - Act 4",
+            Self::SweetMountainAct5 => "Sweet Mountain - Act 5",
+            Self::SweetMountainAct6 => "Sweet Mountain - Act 6",
+            Self::SweetMountainBoss => "Sweet Mountain - Boss",
+            Self::StarlightCarnivalAct1 => "Starlight Carnival - Act 1",
+            Self::StarlightCarnivalAct2 => "Starlight Carnival - Act 2",
+            Self::StarlightCarnivalAct3 => "Starlight Carnival - Act 3",
+            Self::StarlightCarnivalAct4 => "Starlight Carnival - Act 4",
+            Self::StarlightCarnivalAct5 => "Starlight Carnival - Act 5",
+            Self::StarlightCarnivalAct6 => "Starlight Carnival - Act 6",
+            Self::StarlightCarnivalBoss => "Starlight Carnival - Boss",
+            Self::PlanetWispAct1 => "Planet Wisp - Act 1",
+            Self::PlanetWispAct2 => "Planet Wisp - Act 2",
+            Self::PlanetWispAct3 => "Planet Wisp - Act 3",
+            Self::PlanetWispAct4 => "Planet Wisp - Act 4",
+            Self::PlanetWispAct5 => "Planet Wisp - Act 5",
+            Self::PlanetWispAct6 => "Planet Wisp - Act 6",
+            Self::PlanetWispBoss => "Planet Wisp - Boss",
+            Self::AquariumParkAct1 => "Aquarium Park - Act 1",
+            Self::AquariumParkAct2 => "Aquarium Park - Act 2",
+            Self::AquariumParkAct3 => "Aquarium Park - Act 3",
+            Self::AquariumParkAct4 => "Aquarium Park - Act 4",
+            Self::AquariumParkAct5 => "Aquarium Park - Act 5",
+            Self::AquariumParkAct6 => "Aquarium Park - Act 6",
+            Self::AquariumParkBoss => "Aquarium Park - Boss",
+            Self::AsteroidCoasterAct1 => "Asteroid Coaster - Act 1",
+            Self::AsteroidCoasterAct2 => "Asteroid Coaster - Act 2",
+            Self::AsteroidCoasterAct3 => "Asteroid Coaster - Act 3",
+            Self::AsteroidCoasterAct4 => "Asteroid Coaster - Act 4",
+            Self::AsteroidCoasterAct5 => "Asteroid Coaster - Act 5",
+            Self::AsteroidCoasterAct6 => "Asteroid Coaster - Act 6",
+            Self::AsteroidCoasterBoss => "Asteroid Coaster - Boss",
+            Self::TerminalVelocityAct1 => "Terminal Velocity - Act 1",
+            Self::TerminalVelocityBoss => "Terminal Velocity - Boss",
+            Self::TerminalVelocityAct2 => "Terminal Velocity - Act 2",
+            Self::SonicSimulatorAct1_1 => "Sonic Simulator 1-1",
+            Self::SonicSimulatorAct1_2 => "Sonic Simulator 1-2",
+            Self::SonicSimulatorAct1_3 => "Sonic Simulator 1-3",
+            Self::SonicSimulatorAct2_1 => "Sonic Simulator 2-1",
+            Self::SonicSimulatorAct2_2 => "Sonic Simulator 2-2",
+            Self::SonicSimulatorAct2_3 => "Sonic Simulator 2-3",
+            Self::SonicSimulatorAct3_1 => "Sonic Simulator 3-1",
+            Self::SonicSimulatorAct3_2 => "Sonic Simulator 3-2",
+            Self::SonicSimulatorAct3_3 => "Sonic Simulator 3-3",
+            Self::SonicSimulatorAct4_1 => "Sonic Simulator 4-1",
+            Self::SonicSimulatorAct4_2 => "Sonic Simulator 4-2",
+            Self::SonicSimulatorAct4_3 => "Sonic Simulator 4-3",
+            Self::SonicSimulatorAct5_1 => "Sonic Simulator 5-1",
+            Self::SonicSimulatorAct5_2 => "Sonic Simulator 5-2",
+            Self::SonicSimulatorAct5_3 => "Sonic Simulator 5-3",
+            Self::SonicSimulatorAct6_1 => "Sonic Simulator 6-1",
+            Self::SonicSimulatorAct6_2 => "Sonic Simulator 6-2",
+            Self::SonicSimulatorAct6_3 => "Sonic Simulator 6-3",
+            Self::SonicSimulatorAct7_1 => "Sonic Simulator 7-1",
+            Self::SonicSimulatorAct7_2 => "Sonic Simulator 7-2",
+            Self::SonicSimulatorAct7_3 => "Sonic Simulator 7-3",
+            Self::RivalRushTropicalResort => "Rival Rush - Tropical Resort",
+            Self::RivalRushSweetMountain => "Rival Rush - Sweet Mountain",
+            Self::RivalRushStarlightCarnival => "Rival Rush - Starlight Carnival",
+            Self::RivalRushPlanetWisp => "Rival Rush - Planet Wisp",
+            Self::RivalRushAquariumPark => "Rival Rush - Aquarium Park",
+            Self::RivalRushAsteroidCoaster => "Rival Rush - Asteroid Coaster",
+            Self::None => "None",
+        }
+    }
+
+    const fn zone(self) -> Option<Zone> {
+        match self {
+            Self::TropicalResortAct1
+            | Self::TropicalResortAct2
+            | Self::TropicalResortAct3
+            | Self::TropicalResortAct4
+            | Self::TropicalResortAct5
+            | Self::TropicalResortAct6
+            | Self::TropicalResortBoss => Some(Zone::TropicalResort),
+            Self::SweetMountainAct1
+            | Self::SweetMountainAct2
+            | Self::SweetMountainAct3
+            | Self::SweetMountainAct4
+            | Self::SweetMountainAct5
+            | Self::SweetMountainAct6
+            | Self::SweetMountainBoss => Some(Zone::SweetMountain),
+            Self::StarlightCarnivalAct1
+            | Self::StarlightCarnivalAct2
+            | Self::StarlightCarnivalAct3
+            | Self::StarlightCarnivalAct4
+            | Self::StarlightCarnivalAct5
+            | Self::StarlightCarnivalAct6
+            | Self::StarlightCarnivalBoss => Some(Zone::StarlightCarnival),
+            Self::PlanetWispAct1
+            | Self::PlanetWispAct2
+            | Self::PlanetWispAct3
+            | Self::PlanetWispAct4
+            | Self::PlanetWispAct5
+            | Self::PlanetWispAct6
+            | Self::PlanetWispBoss => Some(Zone::PlanetWisp),
+            Self::AquariumParkAct1
+            | Self::AquariumParkAct2
+            | Self::AquariumParkAct3
+            | Self::AquariumParkAct4
+            | Self::AquariumParkAct5
+            | Self::AquariumParkAct6
+            | Self::AquariumParkBoss => Some(Zone::AquariumPark),
+            Self::AsteroidCoasterAct1
+            | Self::AsteroidCoasterAct2
+            | Self::AsteroidCoasterAct3
+            | Self::AsteroidCoasterAct4
+            | Self::AsteroidCoasterAct5
+            | Self::AsteroidCoasterAct6
+            | Self::AsteroidCoasterBoss => Some(Zone::AsteroidCoaster),
+            Self::TerminalVelocityAct1
+            | Self::TerminalVelocityBoss
+            | Self::TerminalVelocityAct2 => Some(Zone::TerminalVelocity),
+            _ => None,
+        }
+    }
+
+    const fn is_rival_rush(self) -> bool {
+        matches!(
+            self,
+            Self::RivalRushTropicalResort
+                | Self::RivalRushSweetMountain
+                | Self::RivalRushStarlightCarnival
+                | Self::RivalRushPlanetWisp
+                | Self::RivalRushAquariumPark
+                | Self::RivalRushAsteroidCoaster
+        )
+    }
+
+    const fn is_boss(self) -> bool {
+        matches!(
+            self,
+            Self::TropicalResortBoss
+                | Self::SweetMountainBoss
+                | Self::StarlightCarnivalBoss
+                | Self::PlanetWispBoss
+                | Self::AquariumParkBoss
+                | Self::AsteroidCoasterBoss
+                | Self::TerminalVelocityBoss
+        )
+    }
+
+    const fn is_sonic_simulator(self) -> bool {
+        self.sonic_simulator_act().is_some()
+    }
+
+    /// World and act number of a Sonic Simulator stage
+    const fn sonic_simulator_act(self) -> Option<(u8, u8)> {
+        match self {
+            Self::SonicSimulatorAct1_1 => Some((1, 1)),
+            Self::SonicSimulatorAct1_2 => Some((1, 2)),
+            Self::SonicSimulatorAct1_3 => Some((1, 3)),
+            Self::SonicSimulatorAct2_1 => Some((2, 1)),
+            Self::SonicSimulatorAct2_2 => Some((2, 2)),
+            Self::SonicSimulatorAct2_3 => Some((2, 3)),
+            Self::SonicSimulatorAct3_1 => Some((3, 1)),
+            Self::SonicSimulatorAct3_2 => Some((3, 2)),
+            Self::SonicSimulatorAct3_3 => Some((3, 3)),
+            Self::SonicSimulatorAct4_1 => Some((4, 1)),
+            Self::SonicSimulatorAct4_2 => Some((4, 2)),
+            Self::SonicSimulatorAct4_3 => Some((4, 3)),
+            Self::SonicSimulatorAct5_1 => Some((5, 1)),
+            Self::SonicSimulatorAct5_2 => Some((5, 2)),
+            Self::SonicSimulatorAct5_3 => Some((5, 3)),
+            Self::SonicSimulatorAct6_1 => Some((6, 1)),
+            Self::SonicSimulatorAct6_2 => Some((6, 2)),
+            Self::SonicSimulatorAct6_3 => Some((6, 3)),
+            Self::SonicSimulatorAct7_1 => Some((7, 1)),
+            Self::SonicSimulatorAct7_2 => Some((7, 2)),
+            Self::SonicSimulatorAct7_3 => Some((7, 3)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn in_stage(level: Levels, igt: Duration) -> Snapshot {
+        Snapshot {
+            level,
+            igt,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn failed_reads_hold_the_last_snapshot() {
+        let mut watchers = Watchers::default();
+        update_watchers(
+            &mut watchers,
+            in_stage(Levels::TropicalResortAct1, Duration::seconds(41)),
+        );
+        update_watchers(
+            &mut watchers,
+            in_stage(Levels::TropicalResortAct1, Duration::seconds(42)),
+        );
+
+        // Everything reads as zero when the reads fail
+        let snapshot = merge_snapshot(watchers.snapshot, Snapshot::default(), true);
+        assert!(snapshot.level == Levels::TropicalResortAct1);
+        assert_eq!(snapshot.igt, Duration::seconds(42));
+
+        update_watchers(&mut watchers, snapshot);
+        assert!(!accumulate_igt(&mut watchers));
+        assert_eq!(watchers.accumulatedigt, Duration::ZERO);
+    }
+
+    #[test]
+    fn successful_reads_replace_the_last_snapshot() {
+        let mut watchers = Watchers::default();
+        update_watchers(
+            &mut watchers,
+            in_stage(Levels::TropicalResortAct1, Duration::seconds(42)),
+        );
+
+        // A real IGT reset, e.g. when the next act is loaded
+        let snapshot = merge_snapshot(
+            watchers.snapshot,
+            in_stage(Levels::TropicalResortAct2, Duration::ZERO),
+            false,
+        );
+        assert!(snapshot.level == Levels::TropicalResortAct2);
+
+        update_watchers(&mut watchers, snapshot);
+        assert!(accumulate_igt(&mut watchers));
+        assert_eq!(watchers.accumulatedigt, Duration::seconds(42));
+    }
+
+    #[test]
+    fn failed_reads_without_a_previous_snapshot_use_the_read_values() {
+        let snapshot = merge_snapshot(None, Snapshot::default(), true);
+        assert!(snapshot.level == Levels::None);
+        assert_eq!(snapshot.igt, Duration::ZERO);
+    }
+
+    #[test]
+    fn level_loads_split_for_the_stage_cleared_before_the_world_map() {
+        let mut watchers = Watchers::default();
+        watchers.levelcleared.update_infallible(true);
+        watchers.clearedlevel = Levels::TropicalResortAct1;
+        watchers.clearedstage = 3;
+        // Progress moved on at the results screen, then the world map was shown
+        watchers.eggshuttle_progressiveid.update_infallible(4);
+        watchers.eggshuttle_progressiveid.update_infallible(4);
+        watchers.levelid.update_infallible(Levels::None);
+        watchers
+            .levelid
+            .update_infallible(Levels::SweetMountainAct1);
+
+        assert!(level_loaded(&watchers));
+        assert!(split_target(&watchers) == (Levels::TropicalResortAct1, 3));
+    }
+
+    #[test]
+    fn other_updates_split_for_the_stage_being_left() {
+        let mut watchers = Watchers::default();
+        watchers.levelcleared.update_infallible(true);
+        watchers.clearedlevel = Levels::TropicalResortAct1;
+        watchers.clearedstage = 3;
+        watchers.eggshuttle_progressiveid.update_infallible(3);
+        watchers.eggshuttle_progressiveid.update_infallible(4);
+        watchers
+            .levelid
+            .update_infallible(Levels::TropicalResortAct1);
+        watchers
+            .levelid
+            .update_infallible(Levels::TropicalResortAct1);
+
+        assert!(!level_loaded(&watchers));
+        assert!(split_target(&watchers) == (Levels::TropicalResortAct1, 3));
+    }
+
+    #[test]
+    fn stage_flags_show_unnamed_bits_by_position() {
+        let mut flags = ArrayString::<64>::new();
+        let _ = write!(flags, "{}", StageFlags(0b0010_1001));
+        assert_eq!(flags.as_str(), "bit 0, bit 3, goal ring");
+
+        flags.clear();
+        let _ = write!(flags, "{}", StageFlags::default());
+        assert_eq!(flags.as_str(), "none");
+    }
+
+    #[test]
+    fn stage_lists_are_parsed_from_one() {
+        assert_eq!(parse_stage_list("1, 3"), 0b101);
+        assert_eq!(parse_stage_list("2-4"), 0b1110);
+        assert_eq!(parse_stage_list(" 45 "), 1 << 44);
+    }
+
+    #[test]
+    fn stage_lists_skip_invalid_entries() {
+        assert_eq!(parse_stage_list(""), 0);
+        assert_eq!(parse_stage_list("0, 46, 300, x, 2"), 0b10);
+        assert_eq!(parse_stage_list("44-60"), 0b11 << 43);
+        assert_eq!(parse_stage_list("5-3"), 0);
+    }
+
+    #[test]
+    fn egg_shuttle_counters_need_a_valid_stage_count() {
+        assert!(!egg_shuttle_counters_plausible((10, 3), (0, 3)));
+        assert!(!egg_shuttle_counters_plausible((10, 3), (46, 3)));
+        assert!(!egg_shuttle_counters_plausible((10, 3), (255, 3)));
+        assert!(egg_shuttle_counters_plausible((45, 3), (45, 4)));
+    }
+
+    #[test]
+    fn egg_shuttle_counters_keep_the_stage_count() {
+        assert!(!egg_shuttle_counters_plausible((10, 3), (12, 3)));
+        // Nothing read yet
+        assert!(egg_shuttle_counters_plausible((0, 0), (10, 0)));
+    }
+
+    #[test]
+    fn egg_shuttle_progress_stays_below_the_stage_count() {
+        assert!(!egg_shuttle_counters_plausible((10, 9), (10, 10)));
+        assert!(!egg_shuttle_counters_plausible((10, 3), (10, 200)));
+    }
+
+    #[test]
+    fn egg_shuttle_progress_advances_one_stage_at_a_time() {
+        assert!(egg_shuttle_counters_plausible((10, 3), (10, 3)));
+        assert!(egg_shuttle_counters_plausible((10, 3), (10, 4)));
+        assert!(!egg_shuttle_counters_plausible((10, 3), (10, 5)));
+        assert!(!egg_shuttle_counters_plausible((10, 3), (10, 2)));
+    }
+
+    #[test]
+    fn egg_shuttle_progress_can_restart() {
+        assert!(egg_shuttle_counters_plausible((10, 7), (10, 0)));
+    }
+
+    #[test]
+    fn egg_shuttle_final_stage_is_plausible_and_recognised() {
+        // Entering the final stage is plausible, going past it is not
+        assert!(egg_shuttle_counters_plausible((10, 8), (10, 9)));
+        assert!(!egg_shuttle_counters_plausible((10, 9), (10, 10)));
+
+        assert!(egg_shuttle_final_stage(9, 10));
+        assert!(!egg_shuttle_final_stage(8, 10));
+        assert!(!egg_shuttle_final_stage(10, 10));
+        assert!(egg_shuttle_final_stage(0, 1));
+        assert!(!egg_shuttle_final_stage(u8::MAX, 0));
+    }
+}